@@ -0,0 +1,754 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+
+use crate::settings::AutostartSettings;
+
+/*
+    开机自启动，按平台使用不同的方式：
+    Windows   HKCU\Software\Microsoft\Windows\CurrentVersion\Run 注册表项
+    Linux     ~/.config/autostart/sc2replay-autofix.desktop（图形界面）
+              ~/.config/systemd/user/sc2replay-autofix.service（无界面守护进程）
+    macOS     ~/Library/LaunchAgents/com.cairbin.sc2replay-autofix.plist
+
+    除注册表外都只读写文件，构造时传入目录即可在临时目录中测试
+*/
+
+// 自启动项的名称
+const APP_ID: &str = "sc2replay-autofix";
+const APP_NAME: &str = "SC2Replay修复工具";
+const LAUNCH_AGENT_LABEL: &str = "com.cairbin.sc2replay-autofix";
+
+// 自启动时执行的程序和参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchSpec {
+    pub exe: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl LaunchSpec {
+    // 启动图形界面，minimized时启动后最小化窗口
+    pub fn gui(exe: PathBuf, minimized: bool) -> Self {
+        let args = if minimized { vec!["--minimized".to_string()] } else { Vec::new() };
+        Self { exe, args }
+    }
+
+    // 不打开窗口，以守护进程方式在后台监控
+    pub fn headless(exe: PathBuf) -> Self {
+        Self { exe, args: vec!["daemon".to_string()] }
+    }
+
+    // 按设置生成启动参数，如 --minimized --profile 解说
+    pub fn from_settings(exe: PathBuf, options: &AutostartSettings) -> Self {
+        let launch = if options.headless { Self::headless(exe) } else { Self::gui(exe, options.minimized) };
+        launch.with_profile(options.profile.as_deref())
+    }
+
+    pub fn with_profile(mut self, profile: Option<&str>) -> Self {
+        if let Some(profile) = profile {
+            self.args.push("--profile".to_string());
+            self.args.push(profile.to_string());
+        }
+        self
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.args.first().map(|a| a == "daemon").unwrap_or(false)
+    }
+
+    fn to_vec(&self) -> Vec<String> {
+        let mut all = vec![self.exe.to_string_lossy().to_string()];
+        all.extend(self.args.iter().cloned());
+        all
+    }
+
+    fn from_vec(mut all: Vec<String>) -> Option<Self> {
+        if all.is_empty() {
+            return None;
+        }
+        let exe = PathBuf::from(all.remove(0));
+        Some(Self { exe, args: all })
+    }
+}
+
+// 一种自启动方式
+pub trait AutostartBackend {
+    // 用于日志的名称
+    fn name(&self) -> &'static str;
+
+    // 写入自启动项，已存在时覆盖
+    fn enable(&self, launch: &LaunchSpec) -> anyhow::Result<()>;
+
+    // 删除自启动项，不存在时不报错
+    fn disable(&self) -> anyhow::Result<()>;
+
+    // 读取当前的自启动项，未启用时返回None
+    fn read(&self) -> anyhow::Result<Option<LaunchSpec>>;
+}
+
+// Windows注册表Run项
+#[cfg(windows)]
+pub struct WindowsRegistry {
+    key: String,
+    value: String,
+}
+
+#[cfg(windows)]
+impl Default for WindowsRegistry {
+    fn default() -> Self {
+        Self {
+            key: "Software\\Microsoft\\Windows\\CurrentVersion\\Run".to_string(),
+            value: "SC2ReplayFixer".to_string(),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl AutostartBackend for WindowsRegistry {
+    fn name(&self) -> &'static str {
+        "注册表"
+    }
+
+    fn enable(&self, launch: &LaunchSpec) -> anyhow::Result<()> {
+        use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER).create_subkey(&self.key)?;
+        key.set_value(&self.value, &windows_command_line(&launch.to_vec()))?;
+        Ok(())
+    }
+
+    fn disable(&self) -> anyhow::Result<()> {
+        use winreg::{enums::{HKEY_CURRENT_USER, KEY_SET_VALUE}, RegKey};
+
+        if let Ok(key) = RegKey::predef(HKEY_CURRENT_USER).open_subkey_with_flags(&self.key, KEY_SET_VALUE) {
+            key.delete_value(&self.value).ok();
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> anyhow::Result<Option<LaunchSpec>> {
+        use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+        let Ok(key) = RegKey::predef(HKEY_CURRENT_USER).open_subkey(&self.key) else {
+            return Ok(None);
+        };
+        match key.get_value::<String, _>(&self.value) {
+            Ok(line) => Ok(LaunchSpec::from_vec(split_windows_command_line(&line))),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+// XDG自启动目录中的.desktop文件，桌面环境登录时启动
+pub struct XdgAutostart {
+    dir: PathBuf,
+}
+
+impl XdgAutostart {
+    // config_dir为~/.config或$XDG_CONFIG_HOME
+    pub fn new(config_dir: &Path) -> Self {
+        Self { dir: config_dir.join("autostart") }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(format!("{}.desktop", APP_ID))
+    }
+}
+
+impl AutostartBackend for XdgAutostart {
+    fn name(&self) -> &'static str {
+        "XDG自启动"
+    }
+
+    fn enable(&self, launch: &LaunchSpec) -> anyhow::Result<()> {
+        // 桌面文件的字符串值中反斜杠需要再转义一次
+        let exec = unix_command_line(&launch.to_vec()).replace('\\', "\\\\");
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+            APP_NAME, exec
+        );
+        write_file(&self.path(), &content)
+    }
+
+    fn disable(&self) -> anyhow::Result<()> {
+        remove_file(&self.path())
+    }
+
+    fn read(&self) -> anyhow::Result<Option<LaunchSpec>> {
+        let Some(content) = read_file(&self.path())? else { return Ok(None) };
+
+        // 用户在桌面环境中关闭的自启动项会被标记为Hidden
+        if content.lines().any(|l| l.trim() == "Hidden=true") {
+            return Ok(None);
+        }
+        Ok(content
+            .lines()
+            .find_map(|l| l.strip_prefix("Exec="))
+            .and_then(|exec| LaunchSpec::from_vec(split_unix_command_line(&exec.replace("\\\\", "\\")))))
+    }
+}
+
+// systemd用户服务，适合没有桌面环境时以守护进程运行
+pub struct SystemdUser {
+    dir: PathBuf,
+}
+
+impl SystemdUser {
+    // config_dir为~/.config或$XDG_CONFIG_HOME
+    pub fn new(config_dir: &Path) -> Self {
+        Self { dir: config_dir.join("systemd").join("user") }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(format!("{}.service", APP_ID))
+    }
+
+    // 与systemctl --user enable创建的链接相同
+    fn wants_link(&self) -> PathBuf {
+        self.dir.join("default.target.wants").join(format!("{}.service", APP_ID))
+    }
+}
+
+impl AutostartBackend for SystemdUser {
+    fn name(&self) -> &'static str {
+        "systemd用户服务"
+    }
+
+    fn enable(&self, launch: &LaunchSpec) -> anyhow::Result<()> {
+        let content = format!(
+            "[Unit]\nDescription={}\n\n[Service]\nType=simple\nExecStart={}\nRestart=on-failure\n\n[Install]\nWantedBy=default.target\n",
+            APP_NAME,
+            unix_command_line(&launch.to_vec())
+        );
+        write_file(&self.path(), &content)?;
+
+        let link = self.wants_link();
+        if let Some(parent) = link.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let _ = fs::remove_file(&link);
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(self.path(), &link)
+            .with_context(|| format!("无法启用服务: {}", link.display()))?;
+        #[cfg(not(unix))]
+        fs::copy(self.path(), &link).with_context(|| format!("无法启用服务: {}", link.display()))?;
+        Ok(())
+    }
+
+    fn disable(&self) -> anyhow::Result<()> {
+        remove_file(&self.wants_link())?;
+        remove_file(&self.path())
+    }
+
+    fn read(&self) -> anyhow::Result<Option<LaunchSpec>> {
+        // 服务文件还在但没有启用时视为未启用
+        if fs::symlink_metadata(self.wants_link()).is_err() {
+            return Ok(None);
+        }
+        let Some(content) = read_file(&self.path())? else { return Ok(None) };
+        Ok(content
+            .lines()
+            .find_map(|l| l.strip_prefix("ExecStart="))
+            .and_then(|exec| LaunchSpec::from_vec(split_unix_command_line(exec))))
+    }
+}
+
+// macOS的LaunchAgent，用户登录时由launchd启动
+pub struct LaunchAgent {
+    dir: PathBuf,
+}
+
+impl LaunchAgent {
+    pub fn new(home: &Path) -> Self {
+        Self { dir: home.join("Library").join("LaunchAgents") }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(format!("{}.plist", LAUNCH_AGENT_LABEL))
+    }
+}
+
+impl AutostartBackend for LaunchAgent {
+    fn name(&self) -> &'static str {
+        "LaunchAgent"
+    }
+
+    fn enable(&self, launch: &LaunchSpec) -> anyhow::Result<()> {
+        let arguments: String = launch
+            .to_vec()
+            .iter()
+            .map(|a| format!("        <string>{}</string>\n", xml_escape(a)))
+            .collect();
+        let content = format!(
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+                "<plist version=\"1.0\">\n",
+                "<dict>\n",
+                "    <key>Label</key>\n",
+                "    <string>{}</string>\n",
+                "    <key>ProgramArguments</key>\n",
+                "    <array>\n",
+                "{}",
+                "    </array>\n",
+                "    <key>RunAtLoad</key>\n",
+                "    <true/>\n",
+                "</dict>\n",
+                "</plist>\n"
+            ),
+            LAUNCH_AGENT_LABEL, arguments
+        );
+        write_file(&self.path(), &content)
+    }
+
+    fn disable(&self) -> anyhow::Result<()> {
+        remove_file(&self.path())
+    }
+
+    fn read(&self) -> anyhow::Result<Option<LaunchSpec>> {
+        let Some(content) = read_file(&self.path())? else { return Ok(None) };
+
+        // 只解析本程序写入的格式：ProgramArguments之后的第一个array
+        let Some(start) = content.find("<key>ProgramArguments</key>") else { return Ok(None) };
+        let rest = &content[start..];
+        let (Some(open), Some(close)) = (rest.find("<array>"), rest.find("</array>")) else {
+            return Ok(None);
+        };
+        let arguments = rest[open..close]
+            .split("<string>")
+            .skip(1)
+            .filter_map(|s| s.split("</string>").next())
+            .map(xml_unescape)
+            .collect();
+        Ok(LaunchSpec::from_vec(arguments))
+    }
+}
+
+// 当前平台使用的自启动方式，headless时Linux使用systemd用户服务
+pub fn default_backend(headless: bool) -> anyhow::Result<Box<dyn AutostartBackend>> {
+    #[cfg(windows)]
+    {
+        let _ = headless;
+        Ok(Box::new(WindowsRegistry::default()))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = headless;
+        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("无法确定用户主目录"))?;
+        Ok(Box::new(LaunchAgent::new(&home)))
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let config_dir = dirs::config_dir().ok_or_else(|| anyhow::anyhow!("无法确定配置目录"))?;
+        if headless {
+            Ok(Box::new(SystemdUser::new(&config_dir)))
+        } else {
+            Ok(Box::new(XdgAutostart::new(&config_dir)))
+        }
+    }
+}
+
+// 自启动项的状态
+#[derive(Debug, Clone, PartialEq)]
+pub enum AutostartStatus {
+    Disabled,
+    Enabled(LaunchSpec),   // 指向当前程序
+    StalePath(LaunchSpec), // 指向的程序已不存在，通常是程序被移动或删除
+    OtherCopy(LaunchSpec), // 指向另一份仍然存在的程序
+}
+
+impl AutostartStatus {
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, AutostartStatus::Disabled)
+    }
+
+    // 自启动项存在但不会启动当前程序
+    pub fn needs_repair(&self) -> bool {
+        matches!(self, AutostartStatus::StalePath(_) | AutostartStatus::OtherCopy(_))
+    }
+
+    pub fn launch(&self) -> Option<&LaunchSpec> {
+        match self {
+            AutostartStatus::Disabled => None,
+            AutostartStatus::Enabled(launch)
+            | AutostartStatus::StalePath(launch)
+            | AutostartStatus::OtherCopy(launch) => Some(launch),
+        }
+    }
+
+    // 用于命令行JSON输出的标识
+    pub fn id(&self) -> &'static str {
+        match self {
+            AutostartStatus::Disabled => "disabled",
+            AutostartStatus::Enabled(_) => "enabled",
+            AutostartStatus::StalePath(_) => "stale_path",
+            AutostartStatus::OtherCopy(_) => "other_copy",
+        }
+    }
+}
+
+impl fmt::Display for AutostartStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutostartStatus::Disabled => write!(f, "未启用"),
+            AutostartStatus::Enabled(_) => write!(f, "已启用"),
+            AutostartStatus::StalePath(launch) => write!(f, "程序已不存在: {}", launch.exe.display()),
+            AutostartStatus::OtherCopy(launch) => write!(f, "指向另一份程序: {}", launch.exe.display()),
+        }
+    }
+}
+
+// 对比自启动项与当前程序exe
+pub fn check_status(backend: &dyn AutostartBackend, exe: &Path) -> anyhow::Result<AutostartStatus> {
+    let Some(launch) = backend.read()? else { return Ok(AutostartStatus::Disabled) };
+
+    if !launch.exe.is_file() {
+        return Ok(AutostartStatus::StalePath(launch));
+    }
+    if same_file(&launch.exe, exe) {
+        Ok(AutostartStatus::Enabled(launch))
+    } else {
+        Ok(AutostartStatus::OtherCopy(launch))
+    }
+}
+
+// 把自启动项改为启动exe，保留原有的启动参数
+pub fn repair_entry(backend: &dyn AutostartBackend, exe: &Path) -> anyhow::Result<LaunchSpec> {
+    let Some(mut launch) = backend.read()? else {
+        return Err(anyhow::anyhow!("未设置开机自启动"));
+    };
+    launch.exe = exe.to_path_buf();
+    backend.enable(&launch)?;
+    Ok(launch)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// 自启动的程序：图形界面程序本身；从命令行程序调用时为同目录下的图形界面程序
+pub fn app_exe() -> anyhow::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    if exe.file_stem().map(|s| s == "sc2replay-cli").unwrap_or(false) {
+        let gui = exe.with_file_name(format!("sc2replay-autofix{}", std::env::consts::EXE_SUFFIX));
+        if gui.is_file() {
+            return Ok(gui);
+        }
+    }
+    Ok(exe)
+}
+
+// 按设置写入或删除自启动项
+pub fn set_auto_start(enable: bool, options: &AutostartSettings) -> anyhow::Result<()> {
+    let backend = default_backend(options.headless)?;
+
+    // Linux上界面和无界面使用不同方式，切换时删除另一种方式的自启动项
+    let other = default_backend(!options.headless)?;
+    if other.name() != backend.name() {
+        other.disable()?;
+    }
+
+    if enable {
+        backend.enable(&LaunchSpec::from_settings(app_exe()?, options))
+    } else {
+        backend.disable()
+    }
+}
+
+// 自启动项所在的方式：优先按设置查找，Linux上找不到时再查找另一种方式
+fn active_backend(options: &AutostartSettings) -> anyhow::Result<Box<dyn AutostartBackend>> {
+    let backend = default_backend(options.headless)?;
+    let other = default_backend(!options.headless)?;
+    if other.name() != backend.name() && backend.read()?.is_none() && other.read()?.is_some() {
+        return Ok(other);
+    }
+    Ok(backend)
+}
+
+/// 检查当前程序的开机自启动状态
+pub fn auto_start_status(options: &AutostartSettings) -> anyhow::Result<AutostartStatus> {
+    check_status(active_backend(options)?.as_ref(), &app_exe()?)
+}
+
+// 修复自启动项，使其启动当前程序
+pub fn repair_auto_start(options: &AutostartSettings) -> anyhow::Result<LaunchSpec> {
+    repair_entry(active_backend(options)?.as_ref(), &app_exe()?)
+}
+
+fn write_file(path: &Path, content: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content).with_context(|| format!("无法写入自启动项: {}", path.display()))
+}
+
+fn read_file(path: &Path) -> anyhow::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("无法读取自启动项: {}", path.display())),
+    }
+}
+
+fn remove_file(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("无法删除自启动项: {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+// 桌面文件Exec和systemd ExecStart的参数引用：含空白或特殊字符时加双引号，
+// 引号内转义 " ` $ \，%写为%%
+fn unix_command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            let arg = arg.replace('%', "%%");
+            let special = |c: char| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c);
+            if !arg.is_empty() && !arg.contains(special) {
+                return arg;
+            }
+            let mut quoted = String::from("\"");
+            for c in arg.chars() {
+                if "\"`$\\".contains(c) {
+                    quoted.push('\\');
+                }
+                quoted.push(c);
+            }
+            quoted.push('"');
+            quoted
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn split_unix_command_line(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut chars = line.trim().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            '\\' if quoted => current.extend(chars.next()),
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                current.push('%');
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+// Windows命令行：含空白的参数加双引号，反斜杠是路径分隔符不做转义
+#[cfg(any(windows, test))]
+fn windows_command_line(args: &[String]) -> String {
+    args.iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            // 程序路径总是加引号，与安装程序写入的格式一致
+            if i == 0 || arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("\"{}\"", arg)
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(any(windows, test))]
+fn split_windows_command_line(line: &str) -> Vec<String> {
+    let line = line.trim();
+
+    // 旧版本直接写入不带引号的程序路径
+    if !line.starts_with('"') && line.to_lowercase().ends_with(".exe") {
+        return vec![line.to_string()];
+    }
+
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&quot;", "\"").replace("&gt;", ">").replace("&lt;", "<").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_home(name: &str) -> PathBuf {
+        let home = std::env::temp_dir().join(format!("sc2replay-autofix-test-autostart-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        home
+    }
+
+    // 启用、读取、停用一轮，覆盖界面最小化启动和无界面启动
+    fn check_backend(backend: &dyn AutostartBackend) {
+        assert_eq!(backend.read().unwrap(), None);
+
+        let gui = LaunchSpec::gui(PathBuf::from("/opt/SC2 Tools/sc2replay-autofix"), true);
+        backend.enable(&gui).unwrap();
+        assert_eq!(backend.read().unwrap(), Some(gui));
+
+        let headless = LaunchSpec::headless(PathBuf::from("/usr/bin/sc2replay-autofix"));
+        backend.enable(&headless).unwrap();
+        let read = backend.read().unwrap().unwrap();
+        assert!(read.is_headless());
+        assert_eq!(read, headless);
+
+        backend.disable().unwrap();
+        assert_eq!(backend.read().unwrap(), None);
+        backend.disable().unwrap();
+    }
+
+    #[test]
+    fn test_xdg_autostart() {
+        let home = temp_home("xdg");
+        let backend = XdgAutostart::new(&home.join(".config"));
+        check_backend(&backend);
+
+        backend.enable(&LaunchSpec::gui(PathBuf::from("/usr/bin/sc2replay-autofix"), true)).unwrap();
+        let content = fs::read_to_string(backend.path()).unwrap();
+        assert!(content.contains("Exec=/usr/bin/sc2replay-autofix --minimized\n"));
+
+        // 在桌面环境中关闭后视为未启用
+        fs::write(backend.path(), content + "Hidden=true\n").unwrap();
+        assert_eq!(backend.read().unwrap(), None);
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_systemd_user() {
+        let home = temp_home("systemd");
+        let backend = SystemdUser::new(&home.join(".config"));
+        check_backend(&backend);
+
+        backend.enable(&LaunchSpec::headless(PathBuf::from("/usr/bin/sc2replay-autofix"))).unwrap();
+        let content = fs::read_to_string(backend.path()).unwrap();
+        assert!(content.contains("ExecStart=/usr/bin/sc2replay-autofix daemon\n"));
+        assert!(content.contains("WantedBy=default.target"));
+
+        // 服务文件还在但链接被删除（systemctl --user disable）
+        fs::remove_file(backend.wants_link()).unwrap();
+        assert_eq!(backend.read().unwrap(), None);
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_launch_agent() {
+        let home = temp_home("launchagent");
+        let backend = LaunchAgent::new(&home);
+        check_backend(&backend);
+
+        backend.enable(&LaunchSpec::gui(PathBuf::from("/Applications/A&B.app/sc2replay-autofix"), false)).unwrap();
+        let content = fs::read_to_string(backend.path()).unwrap();
+        assert!(content.contains("<string>/Applications/A&amp;B.app/sc2replay-autofix</string>"));
+        assert!(content.contains("<key>RunAtLoad</key>"));
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_status_and_repair() {
+        let home = temp_home("status");
+        let backend = XdgAutostart::new(&home.join(".config"));
+        let exe = home.join("new").join("sc2replay-autofix");
+        let other = home.join("other").join("sc2replay-autofix");
+        for path in [&exe, &other] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        assert_eq!(check_status(&backend, &exe).unwrap(), AutostartStatus::Disabled);
+        assert!(repair_entry(&backend, &exe).is_err());
+
+        let options = AutostartSettings { minimized: true, profile: Some("解说".to_string()), ..Default::default() };
+        let launch = LaunchSpec::from_settings(exe.clone(), &options);
+        assert_eq!(launch.args, vec!["--minimized", "--profile", "解说"]);
+        backend.enable(&launch).unwrap();
+        assert_eq!(check_status(&backend, &exe).unwrap(), AutostartStatus::Enabled(launch.clone()));
+
+        backend.enable(&LaunchSpec { exe: other.clone(), ..launch.clone() }).unwrap();
+        assert!(matches!(check_status(&backend, &exe).unwrap(), AutostartStatus::OtherCopy(_)));
+
+        // 程序被移动后自启动项失效，修复时保留启动参数
+        let moved = home.join("old").join("sc2replay-autofix");
+        backend.enable(&LaunchSpec { exe: moved, ..launch.clone() }).unwrap();
+        let status = check_status(&backend, &exe).unwrap();
+        assert!(matches!(status, AutostartStatus::StalePath(_)));
+        assert!(status.needs_repair());
+
+        assert_eq!(repair_entry(&backend, &exe).unwrap(), launch);
+        assert_eq!(check_status(&backend, &exe).unwrap(), AutostartStatus::Enabled(launch));
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_command_line_quoting() {
+        let args: Vec<String> = ["/a b/c", "--profile", "50% \"x\"", ""].iter().map(|s| s.to_string()).collect();
+        assert_eq!(unix_command_line(&args), r#""/a b/c" --profile "50%% \"x\"" """#);
+        assert_eq!(split_unix_command_line(&unix_command_line(&args)), args);
+
+        let args: Vec<String> = ["C:\\Program Files\\SC2\\sc2replay-autofix.exe", "--minimized"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(windows_command_line(&args), "\"C:\\Program Files\\SC2\\sc2replay-autofix.exe\" --minimized");
+        assert_eq!(split_windows_command_line(&windows_command_line(&args)), args);
+        assert_eq!(
+            split_windows_command_line("C:\\Program Files\\SC2\\sc2replay-autofix.exe"),
+            vec!["C:\\Program Files\\SC2\\sc2replay-autofix.exe".to_string()]
+        );
+    }
+}
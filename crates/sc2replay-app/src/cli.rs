@@ -0,0 +1,790 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde_json::json;
+
+use sc2replay_core::builds;
+use sc2replay_core::discovery::{self, ToonHandle, Region};
+use sc2replay_core::events::{Event, EventBus};
+use sc2replay_core::fixer::{self, FixOptions, FixOutcome, RevertAction};
+use sc2replay_core::journal::{self, JournalEntry};
+use sc2replay_core::log::LogLevel;
+use sc2replay_core::replay::{read_details, read_header};
+use sc2replay_core::signature;
+
+use crate::autostart::{self, AutostartStatus};
+use crate::daemon::{self, DaemonClient};
+use crate::instance;
+use crate::logging;
+use crate::message::AppState;
+use crate::settings::{load_settings, load_user_builds, Settings};
+
+// 退出码
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILED: i32 = 1;    // 有文件处理失败
+pub const EXIT_USAGE: i32 = 2;     // 参数错误
+pub const EXIT_NOT_FOUND: i32 = 3; // 文件/目录不存在或没有找到录像目录
+pub const EXIT_ALREADY_RUNNING: i32 = 4; // 已有守护进程或图形界面在监控
+
+const COMMANDS: &[&str] = &["fix", "batch", "watch", "inspect", "discover", "revert", "journal", "derive-rule", "daemon", "ctl", "autostart", "help"];
+
+const USAGE: &str = r#"用法: sc2replay-cli [选项] <命令> [参数...]
+
+图形界面sc2replay-autofix也接受同样的命令，不带命令时启动图形界面。
+
+命令:
+  fix <录像...>      修复指定的录像文件
+  batch [目录...]    修复目录下的所有录像，不指定目录时使用当前配置找到的录像目录
+  watch              监控录像目录并自动修复新录像
+  inspect <录像...>  显示录像的版本、玩家以及是否需要修复
+  discover           列出找到的SC2文档目录和录像目录
+  revert [目录...]   撤销修复：恢复.bak备份，删除生成的修复文件
+  journal [录像...]  显示修复记录，指定录像时只显示与其有关的记录（按路径或内容匹配）
+  derive-rule <坏录像> <正常录像>
+                     （开发用）比较两个录像的版本字段，推导修复规则和测试用例
+  daemon             以后台守护进程运行监控，图形界面启动时会自动连接
+  ctl <方法> [参数]  调用守护进程的控制接口，如 ctl status、ctl logs '{"since":0}'
+  autostart [操作]   开机自启动：status（默认）、enable、disable、repair（改为启动当前程序）
+  help               显示本帮助
+
+选项:
+  --profile <名称>   使用指定的配置
+  --json             以JSON格式输出
+  --dry-run          revert时只列出将执行的操作
+  --interval <秒>    watch时每隔指定秒数输出一次监控状态
+  --all              discover时列出所有SC2文档目录下的录像目录；journal时显示全部记录
+  --no-watch         daemon启动时不立即开始监控
+  --minimized        启动图形界面后最小化窗口（用于开机自启动）
+  --headless         autostart enable时以守护进程方式自启动，不打开窗口
+  --out <目录>       derive-rule时把规则和测试用例写入该目录
+
+退出码: 0 成功，1 有文件处理失败，2 参数错误，3 文件或目录不存在，4 已有实例在运行"#;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliArgs {
+    pub command: Option<String>,
+    pub paths: Vec<PathBuf>,
+    pub profile: Option<String>,
+    pub json: bool,
+    pub dry_run: bool,
+    pub all: bool,
+    pub no_watch: bool,
+    pub minimized: bool,
+    pub headless: bool,
+    pub interval: Option<u64>,
+    pub out: Option<PathBuf>,
+}
+
+pub fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut parsed = CliArgs::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => parsed.json = true,
+            "--dry-run" => parsed.dry_run = true,
+            "--all" => parsed.all = true,
+            "--no-watch" => parsed.no_watch = true,
+            "--minimized" => parsed.minimized = true,
+            "--headless" => parsed.headless = true,
+            "-h" | "--help" => parsed.command = Some("help".to_string()),
+            "--profile" => {
+                parsed.profile = Some(iter.next().ok_or("--profile缺少配置名")?.clone());
+            }
+            "--interval" => {
+                let value = iter.next().ok_or("--interval缺少秒数")?;
+                parsed.interval = Some(value.parse().map_err(|_| format!("无效的秒数: {}", value))?);
+            }
+            "--out" => {
+                parsed.out = Some(PathBuf::from(iter.next().ok_or("--out缺少目录")?));
+            }
+            other if other.starts_with("--profile=") => {
+                parsed.profile = Some(other["--profile=".len()..].to_string());
+            }
+            other if other.starts_with('-') => return Err(format!("未知选项: {}", other)),
+            other if parsed.command.is_none() => {
+                if !COMMANDS.contains(&other) {
+                    return Err(format!("未知命令: {}", other));
+                }
+                parsed.command = Some(other.to_string());
+            }
+            other => parsed.paths.push(PathBuf::from(other)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+pub fn run(args: &CliArgs) -> i32 {
+    // 修复和监控的日志输出到标准错误，标准输出只留给结果；守护进程自己输出日志
+    let bus = EventBus::new();
+    if args.command.as_deref() != Some("daemon") {
+        let events = bus.subscribe();
+        std::thread::spawn(move || {
+            for event in events.iter() {
+                if let Event::Log(record) = event
+                    && record.level >= LogLevel::Info
+                {
+                    eprintln!("{}", record);
+                }
+            }
+        });
+    }
+
+    match args.command.as_deref() {
+        Some("fix") => run_fix(args, &bus),
+        Some("batch") => run_batch(args, &bus),
+        Some("watch") => run_watch(args, &bus),
+        Some("inspect") => run_inspect(args, &bus),
+        Some("discover") => run_discover(args, &bus),
+        Some("revert") => run_revert(args, &bus),
+        Some("journal") => run_journal(args),
+        Some("derive-rule") => run_derive_rule(args),
+        Some("daemon") => run_daemon(args, &bus),
+        Some("ctl") => run_ctl(args),
+        Some("autostart") => run_autostart(args),
+        _ => {
+            println!("{}", USAGE);
+            EXIT_OK
+        }
+    }
+}
+
+pub fn usage() -> &'static str {
+    USAGE
+}
+
+// 按设置文件和--profile准备状态：基础目录、录像目录和修复选项
+fn load_state(args: &CliArgs, bus: &EventBus) -> AppState {
+    let mut settings = load_settings().unwrap_or_else(|e| {
+        eprintln!("[失败]读取设置文件失败，已使用默认设置: {:#}", e);
+        Settings::default()
+    });
+    if let Some(profile) = &args.profile
+        && settings.select_profile(profile)
+    {
+        eprintln!("[失败]配置不存在，使用默认值: {}", profile);
+    }
+
+    let mut state = AppState {
+        replay_dir: discovery::find_sc2_base_dir().unwrap_or_default(),
+        auto_fix: true,
+        bus: bus.clone(),
+        ..AppState::default()
+    };
+    // 守护进程和其他命令分别写各自的日志文件
+    let name = if args.command.as_deref() == Some("daemon") { "daemon" } else { "cli" };
+    if let Err(e) = logging::spawn_file_logger(bus, name, &settings.log) {
+        eprintln!("[失败]{:#}", e);
+    }
+    if let Err(e) = load_user_builds() {
+        eprintln!("[失败]{:#}", e);
+    }
+    state.apply_settings(settings);
+    state
+}
+
+fn print_json(value: &serde_json::Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+}
+
+// 单个文件的处理结果
+struct FileReport {
+    path: PathBuf,
+    result: anyhow::Result<FixOutcome>,
+}
+
+fn fix_paths(paths: &[PathBuf], options: &FixOptions) -> Vec<FileReport> {
+    paths
+        .iter()
+        .map(|path| FileReport {
+            path: path.clone(),
+            result: if path.exists() {
+                fixer::fix_file(path, options)
+            } else {
+                Err(anyhow::anyhow!("文件不存在: {}", path.display()))
+            },
+        })
+        .collect()
+}
+
+fn report_fixes(reports: &[FileReport], json: bool) -> i32 {
+    let mut fixed = 0;
+    let mut failed = 0;
+    let mut items = Vec::new();
+
+    for report in reports {
+        let path = report.path.display();
+        match &report.result {
+            Ok(FixOutcome::Fixed { rule_id, output_path }) => {
+                fixed += 1;
+                items.push(json!({ "path": report.path, "status": "fixed", "rule": rule_id, "output": output_path }));
+                if !json {
+                    println!("[成功]{} -> {} ({})", path, output_path.display(), rule_id);
+                }
+            }
+            Ok(FixOutcome::SkippedOutput) => {
+                items.push(json!({ "path": report.path, "status": "skipped" }));
+                if !json {
+                    println!("[跳过]已是修复后的文件: {}", path);
+                }
+            }
+            Ok(FixOutcome::NoMatch) => {
+                items.push(json!({ "path": report.path, "status": "no_match" }));
+                if !json {
+                    println!("[跳过]未找到目标字节序列: {}", path);
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                items.push(json!({ "path": report.path, "status": "error", "error": format!("{:#}", e) }));
+                if !json {
+                    println!("[失败]{}: {:#}", path, e);
+                }
+            }
+        }
+    }
+
+    if json {
+        print_json(&json!({ "files": items, "fixed": fixed, "failed": failed }));
+    } else {
+        println!("共{}个文件，修复{}个，失败{}个", reports.len(), fixed, failed);
+    }
+
+    if reports.iter().any(|r| r.result.is_err() && !r.path.exists()) {
+        EXIT_NOT_FOUND
+    } else if failed > 0 {
+        EXIT_FAILED
+    } else {
+        EXIT_OK
+    }
+}
+
+fn run_fix(args: &CliArgs, bus: &EventBus) -> i32 {
+    if args.paths.is_empty() {
+        eprintln!("fix需要指定录像文件\n\n{}", USAGE);
+        return EXIT_USAGE;
+    }
+    let state = load_state(args, bus);
+    report_fixes(&fix_paths(&args.paths, &state.fix_options), args.json)
+}
+
+// 命令行指定的目录，未指定时使用当前配置的目录
+fn target_dirs(args: &CliArgs, state: &AppState) -> Result<Vec<PathBuf>, i32> {
+    let dirs = if args.paths.is_empty() { state.fix_dirs() } else { args.paths.clone() };
+    if dirs.is_empty() {
+        eprintln!("[失败]未找到任何录像目录");
+        return Err(EXIT_NOT_FOUND);
+    }
+    if let Some(missing) = dirs.iter().find(|d| !d.is_dir()) {
+        eprintln!("[失败]目录不存在: {}", missing.display());
+        return Err(EXIT_NOT_FOUND);
+    }
+    Ok(dirs)
+}
+
+fn replay_files(dir: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .flatten()
+        .map(|e| e.into_path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("SC2Replay"))
+        .collect()
+}
+
+fn run_batch(args: &CliArgs, bus: &EventBus) -> i32 {
+    let state = load_state(args, bus);
+    let dirs = match target_dirs(args, &state) {
+        Ok(dirs) => dirs,
+        Err(code) => return code,
+    };
+
+    let files: Vec<PathBuf> = dirs.iter().flat_map(|d| replay_files(d)).collect();
+    report_fixes(&fix_paths(&files, &state.fix_options), args.json)
+}
+
+// 已有守护进程或图形界面实例时报告并返回退出码，避免两个监控同时修复同一个录像
+fn check_running() -> Result<(), i32> {
+    if let Some(client) = DaemonClient::connect() {
+        let pid = client.call("status", json!({})).ok().and_then(|s| s["pid"].as_u64()).unwrap_or(0);
+        eprintln!("守护进程已在运行(进程{})，控制接口: {}", pid, daemon::endpoint_path().display());
+        return Err(EXIT_ALREADY_RUNNING);
+    }
+    if let Some(pid) = instance::running_pid() {
+        eprintln!("图形界面已在运行(进程{})，请在界面中监控或先关闭它", pid);
+        return Err(EXIT_ALREADY_RUNNING);
+    }
+    Ok(())
+}
+
+fn run_watch(args: &CliArgs, bus: &EventBus) -> i32 {
+    if let Err(code) = check_running() {
+        return code;
+    }
+    let mut state = load_state(args, bus);
+    if state.monitor_dirs().is_empty() {
+        eprintln!("[失败]未找到任何录像目录");
+        return EXIT_NOT_FOUND;
+    }
+
+    state.start_monitor();
+    let Some(instance) = state.monitor_instance.clone() else {
+        return EXIT_FAILED;
+    };
+    for dir in instance.dirs() {
+        eprintln!("[监控] {}", dir.display());
+    }
+
+    // 一直运行到进程被结束
+    let interval = Duration::from_secs(args.interval.unwrap_or(0).max(1));
+    while instance.is_running() {
+        std::thread::sleep(interval);
+        if args.interval.is_some() {
+            let snapshot = instance.snapshot();
+            if args.json {
+                println!("{}", snapshot.to_json());
+            } else {
+                println!("{}\n", snapshot);
+            }
+        }
+    }
+
+    EXIT_OK
+}
+
+fn run_inspect(args: &CliArgs, bus: &EventBus) -> i32 {
+    if args.paths.is_empty() {
+        eprintln!("inspect需要指定录像文件\n\n{}", USAGE);
+        return EXIT_USAGE;
+    }
+    let state = load_state(args, bus);
+    let rules = &state.fix_options.rules;
+
+    let mut code = EXIT_OK;
+    let mut items = Vec::new();
+    for path in &args.paths {
+        if !path.exists() {
+            eprintln!("[失败]文件不存在: {}", path.display());
+            code = EXIT_NOT_FOUND;
+            continue;
+        }
+
+        let header = match read_header(path) {
+            Ok(header) => header,
+            Err(e) => {
+                eprintln!("[失败]无法读取录像 {}: {:#}", path.display(), e);
+                items.push(json!({ "path": path, "error": format!("{:#}", e) }));
+                if code == EXIT_OK {
+                    code = EXIT_FAILED;
+                }
+                continue;
+            }
+        };
+        let players = read_details(path).map(|d| d.players).unwrap_or_default();
+        let data = std::fs::read(path).unwrap_or_default();
+        let matched = fixer::find_rule(&data, rules).unwrap_or_else(|e| {
+            eprintln!("[失败]{}: {:#}", path.display(), e);
+            None
+        });
+        // 游戏内时间每秒16个游戏循环
+        let seconds = header.elapsed_game_loops / 16;
+        let known = builds::lookup(header.version.base_build);
+
+        if args.json {
+            items.push(json!({
+                "path": path,
+                "version": header.version.to_string(),
+                "base_build": header.version.base_build,
+                "known_version": known,
+                "elapsed_game_loops": header.elapsed_game_loops,
+                "duration_secs": seconds,
+                "players": players.iter().map(|p| json!({
+                    "name": p.name,
+                    "toon": toon_of(p.region, p.realm, p.toon_id),
+                })).collect::<Vec<_>>(),
+                "is_output": fixer::is_output_file(path, &state.fix_options.output_template),
+                "needs_fix": matched.is_some(),
+                "rule": matched.as_ref().map(|(rule, _)| rule.id.clone()),
+                "archive_file": matched.as_ref().and_then(|(_, change)| change.archive_file.clone()),
+                "offset": matched.as_ref().map(|(_, change)| change.offset),
+            }));
+        } else {
+            println!("文件: {}", path.display());
+            println!("版本: {} (base build {})", header.version, header.version.base_build);
+            if known.is_empty() {
+                println!("对应版本: 未知（可在配置目录的builds.toml中补充）");
+            }
+            for info in &known {
+                println!("对应版本: {}", info);
+            }
+            println!("时长: {}:{:02}", seconds / 60, seconds % 60);
+            for player in &players {
+                println!("玩家: {} ({})", player.name, toon_of(player.region, player.realm, player.toon_id));
+            }
+            match matched {
+                Some((rule, change)) => match &change.archive_file {
+                    Some(file) => println!("需要修复: 是 (规则{}，{}偏移{})", rule.id, file, change.offset),
+                    None => println!("需要修复: 是 (规则{}，偏移{})", rule.id, change.offset),
+                },
+                None => println!("需要修复: 否"),
+            }
+            println!();
+        }
+    }
+
+    if args.json {
+        print_json(&json!(items));
+    }
+    code
+}
+
+fn toon_of(region: u32, realm: u32, id: u64) -> String {
+    ToonHandle { region: Region::from_id(region), realm, id }.to_string()
+}
+
+fn run_discover(args: &CliArgs, bus: &EventBus) -> i32 {
+    let state = load_state(args, bus);
+    let base_dirs = discovery::find_sc2_base_dirs();
+    let locations = if args.all {
+        discovery::discover_all_replay_locations(&state.replay_categories.categories)
+    } else {
+        state.replay_locations.clone()
+    };
+
+    if args.json {
+        print_json(&json!({
+            "profile": state.settings.active_profile,
+            "base_dir": state.replay_dir,
+            "base_dirs": base_dirs.iter().map(|b| json!({ "source": b.source.id(), "path": b.path })).collect::<Vec<_>>(),
+            "replay_dirs": locations.iter().map(|l| {
+                let options = state.account_options(&l.account_dir);
+                json!({
+                    "path": l.path,
+                    "name": state.account_name(l),
+                    "account_id": l.account_id,
+                    "toon": l.toon_handle,
+                    "region": l.region.map(|r| r.to_string()),
+                    "category": l.category,
+                    "enabled": state.all_replay_dirs.contains(&l.path),
+                    "fix": options.fix,
+                    "monitor": options.monitor,
+                })
+            }).collect::<Vec<_>>(),
+        }));
+    } else {
+        println!("配置: {}", state.settings.active_profile);
+        println!("基础目录: {}", state.replay_dir.display());
+        for base in &base_dirs {
+            println!("🔍 SC2文档目录({}): {}", base.source, base.path.display());
+        }
+        for location in &locations {
+            let mark = if state.all_replay_dirs.contains(&location.path) { "*" } else { " " };
+            println!("{} {}  {}", mark, state.location_label(location), location.path.display());
+        }
+        println!("共{}个录像目录（*为已启用）", locations.len());
+    }
+
+    if locations.is_empty() { EXIT_NOT_FOUND } else { EXIT_OK }
+}
+
+fn run_revert(args: &CliArgs, bus: &EventBus) -> i32 {
+    let state = load_state(args, bus);
+    let dirs = match target_dirs(args, &state) {
+        Ok(dirs) => dirs,
+        Err(code) => return code,
+    };
+
+    let mut code = EXIT_OK;
+    let mut items = Vec::new();
+    for dir in &dirs {
+        let actions = match fixer::revert_dir(dir, args.dry_run, &state.fix_options) {
+            Ok(actions) => actions,
+            Err(e) => {
+                eprintln!("[失败]撤销失败 {}: {:#}", dir.display(), e);
+                code = EXIT_FAILED;
+                continue;
+            }
+        };
+
+        for action in actions {
+            match action {
+                RevertAction::RestoreBackup { backup, original } => {
+                    items.push(json!({ "action": "restore", "backup": backup, "original": original }));
+                    if !args.json {
+                        println!("恢复备份: {} -> {}", backup.display(), original.display());
+                    }
+                }
+                RevertAction::RemoveOutput { output } => {
+                    items.push(json!({ "action": "remove", "output": output }));
+                    if !args.json {
+                        println!("删除修复文件: {}", output.display());
+                    }
+                }
+            }
+        }
+    }
+
+    if args.json {
+        print_json(&json!({ "dry_run": args.dry_run, "actions": items }));
+    } else if args.dry_run {
+        println!("共{}项操作（未执行）", items.len());
+    } else {
+        println!("共执行{}项操作", items.len());
+    }
+    code
+}
+
+// 不带--all时显示的最近记录数
+const JOURNAL_RECENT: usize = 20;
+
+fn run_journal(args: &CliArgs) -> i32 {
+    let Some(path) = logging::journal_path() else {
+        eprintln!("[失败]无法确定修复记录文件位置");
+        return EXIT_FAILED;
+    };
+    let entries = match journal::read_journal(&path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("[失败]{:#}", e);
+            return EXIT_FAILED;
+        }
+    };
+
+    let mut code = EXIT_OK;
+    let shown: Vec<&JournalEntry> = if args.paths.is_empty() {
+        let skip = if args.all { 0 } else { entries.len().saturating_sub(JOURNAL_RECENT) };
+        entries[skip..].iter().collect()
+    } else {
+        let mut shown: Vec<&JournalEntry> = Vec::new();
+        for file in &args.paths {
+            let found = journal::entries_for_file(&entries, file);
+            if found.is_empty() {
+                eprintln!("没有与该文件有关的修复记录: {}", file.display());
+                code = EXIT_NOT_FOUND;
+            }
+            for entry in found {
+                // 多个文件可能对应同一条记录
+                if !shown.iter().any(|e| std::ptr::eq(*e, entry)) {
+                    shown.push(entry);
+                }
+            }
+        }
+        shown.sort_by_key(|e| e.timestamp_ms);
+        shown
+    };
+
+    if args.json {
+        print_json(&json!({ "journal": path, "entries": shown }));
+    } else {
+        for entry in &shown {
+            println!("{}", entry);
+            if let Some(hash) = &entry.input_hash {
+                println!("    原文件SHA-256: {}", hash);
+            }
+            if let Some(hash) = &entry.output_hash {
+                println!("    输出SHA-256:   {}", hash);
+            }
+        }
+        println!("共{}条记录（{}）", shown.len(), path.display());
+    }
+    code
+}
+
+fn run_derive_rule(args: &CliArgs) -> i32 {
+    let [broken, good] = args.paths.as_slice() else {
+        eprintln!("derive-rule需要指定坏录像和正常录像\n\n{}", USAGE);
+        return EXIT_USAGE;
+    };
+    for path in [broken, good] {
+        if !path.exists() {
+            eprintln!("[失败]文件不存在: {}", path.display());
+            return EXIT_NOT_FOUND;
+        }
+    }
+
+    let derive = || -> anyhow::Result<signature::Derivation> {
+        signature::derive_rule(&std::fs::read(broken)?, &std::fs::read(good)?)
+    };
+    let derivation = match derive() {
+        Ok(derivation) => derivation,
+        Err(e) => {
+            eprintln!("[失败]{:#}", e);
+            return EXIT_FAILED;
+        }
+    };
+    let fixture = match toml::to_string(&derivation.fixture) {
+        Ok(fixture) => fixture,
+        Err(e) => {
+            eprintln!("[失败]{:#}", e);
+            return EXIT_FAILED;
+        }
+    };
+
+    if let Some(dir) = &args.out {
+        let rule_path = dir.join(format!("{}.toml", derivation.rule_id));
+        let fixture_path = dir.join(format!("{}.fixture.toml", derivation.rule_id));
+        let written = std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&rule_path, &derivation.rule_file))
+            .and_then(|_| std::fs::write(&fixture_path, &fixture));
+        if let Err(e) = written {
+            eprintln!("[失败]无法写入{}: {}", dir.display(), e);
+            return EXIT_FAILED;
+        }
+        eprintln!("规则: {}", rule_path.display());
+        eprintln!("测试用例: {}", fixture_path.display());
+    }
+
+    if args.json {
+        print_json(&json!({
+            "rule_id": derivation.rule_id,
+            "diffs": derivation.diffs,
+            "rule": derivation.rule_file,
+            "fixture": derivation.fixture,
+        }));
+    } else if args.out.is_none() {
+        println!("{}", derivation.rule_file);
+        println!("# 测试用例");
+        println!("{}", fixture);
+    } else {
+        for diff in &derivation.diffs {
+            println!("{}: {} -> {}", diff.name, diff.broken, diff.good);
+        }
+    }
+    EXIT_OK
+}
+
+fn run_daemon(args: &CliArgs, bus: &EventBus) -> i32 {
+    if let Err(code) = check_running() {
+        return code;
+    }
+    let events = bus.subscribe();
+    let state = load_state(args, bus);
+    match daemon::run(state, events, !args.no_watch) {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("[失败]{:#}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+fn run_ctl(args: &CliArgs) -> i32 {
+    let Some(method) = args.paths.first().map(|m| m.to_string_lossy().into_owned()) else {
+        eprintln!("ctl需要指定方法\n\n{}", USAGE);
+        return EXIT_USAGE;
+    };
+    let params = match args.paths.get(1) {
+        Some(params) => match serde_json::from_str(&params.to_string_lossy()) {
+            Ok(params) => params,
+            Err(e) => {
+                eprintln!("参数不是有效的JSON: {}", e);
+                return EXIT_USAGE;
+            }
+        },
+        None => json!({}),
+    };
+
+    let Some(client) = DaemonClient::connect() else {
+        eprintln!("守护进程未运行: {}", daemon::endpoint_path().display());
+        return EXIT_NOT_FOUND;
+    };
+    match client.call(&method, params) {
+        Ok(result) => {
+            print_json(&result);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("[失败]{:#}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+// 自启动选项取自设置文件，命令行的--minimized、--headless、--profile优先
+fn run_autostart(args: &CliArgs) -> i32 {
+    let settings = load_settings().unwrap_or_else(|e| {
+        eprintln!("[失败]读取设置文件失败，已使用默认设置: {:#}", e);
+        Settings::default()
+    });
+    let mut options = settings.autostart;
+    options.minimized |= args.minimized;
+    options.headless |= args.headless;
+    if args.profile.is_some() {
+        options.profile = args.profile.clone();
+    }
+
+    let action = args.paths.first().map(|p| p.to_string_lossy().to_string()).unwrap_or_else(|| "status".to_string());
+    let result = match action.as_str() {
+        "status" => Ok(()),
+        "enable" => autostart::set_auto_start(true, &options),
+        "disable" => autostart::set_auto_start(false, &options),
+        "repair" => autostart::repair_auto_start(&options).map(|_| ()),
+        other => {
+            eprintln!("未知操作: {}\n\n{}", other, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("[失败]{:#}", e);
+        return if action == "repair" { EXIT_NOT_FOUND } else { EXIT_FAILED };
+    }
+
+    let status = match autostart::auto_start_status(&options) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("[失败]无法读取自启动项: {:#}", e);
+            return EXIT_FAILED;
+        }
+    };
+    if args.json {
+        let launch = status.launch();
+        print_json(&json!({
+            "status": status.id(),
+            "exe": launch.map(|l| &l.exe),
+            "args": launch.map(|l| &l.args),
+        }));
+    } else {
+        println!("开机自启动: {}", status);
+        if let Some(launch) = status.launch() {
+            println!("启动命令: {} {}", launch.exe.display(), launch.args.join(" "));
+        }
+        if status.needs_repair() {
+            println!("可运行 autostart repair 改为启动当前程序");
+        }
+    }
+
+    if matches!(status, AutostartStatus::Disabled) && action == "enable" { EXIT_FAILED } else { EXIT_OK }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = parse_args(&args(&["--json", "fix", "a.SC2Replay", "--profile", "caster"])).unwrap();
+        assert_eq!(parsed.command.as_deref(), Some("fix"));
+        assert_eq!(parsed.paths, vec![PathBuf::from("a.SC2Replay")]);
+        assert_eq!(parsed.profile.as_deref(), Some("caster"));
+        assert!(parsed.json);
+
+        // 只有--profile时不进入命令行模式
+        let parsed = parse_args(&args(&["--profile=caster", "--minimized"])).unwrap();
+        assert_eq!(parsed.command, None);
+        assert_eq!(parsed.profile.as_deref(), Some("caster"));
+        assert!(parsed.minimized);
+
+        let parsed = parse_args(&args(&["derive-rule", "a.SC2Replay", "b.SC2Replay", "--out", "rules"])).unwrap();
+        assert_eq!(parsed.paths.len(), 2);
+        assert_eq!(parsed.out, Some(PathBuf::from("rules")));
+
+        assert!(parse_args(&args(&["unknown"])).is_err());
+        assert!(parse_args(&args(&["watch", "--interval", "x"])).is_err());
+    }
+}
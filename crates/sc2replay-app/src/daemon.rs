@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use anyhow::Context;
+use crossbeam_channel::Receiver;
+use serde_json::{json, Value};
+
+use crate::ipc::{self, INVALID_PARAMS, METHOD_NOT_FOUND, SERVER_ERROR};
+use sc2replay_core::events::Event;
+use sc2replay_core::log::{LogLevel, LogRecord, LogSource};
+
+use crate::message::AppState;
+use crate::settings::load_settings;
+
+/*
+    后台守护进程的控制接口，每行一个JSON-RPC 2.0请求/响应：
+    -> {"jsonrpc":"2.0","id":1,"method":"status","params":{}}
+    <- {"jsonrpc":"2.0","id":1,"result":{...}}
+
+    方法: status, start, stop, pause, resume, batch, logs, reload, shutdown
+    传输方式见ipc模块
+*/
+
+// 守护进程保留的日志行数
+const MAX_LOG_LINES: usize = 1000;
+
+// 控制接口名称
+const ENDPOINT: &str = "daemon";
+
+pub fn endpoint_path() -> PathBuf {
+    ipc::endpoint_path(ENDPOINT)
+}
+
+// 连接到守护进程的客户端，每次调用新建一个连接
+#[derive(Debug, Clone, Default)]
+pub struct DaemonClient {
+    next_id: Arc<AtomicU64>,
+}
+
+impl DaemonClient {
+    // 守护进程正在运行时返回客户端
+    pub fn connect() -> Option<Self> {
+        let client = Self::default();
+        client.call("status", json!({})).ok().map(|_| client)
+    }
+
+    pub fn call(&self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        ipc::call(ENDPOINT, id, method, params).context("守护进程调用失败")
+    }
+}
+
+// 带序号的日志缓冲，客户端按序号增量读取
+#[derive(Default)]
+struct LogBuffer {
+    records: VecDeque<LogRecord>,
+    next: u64, // 下一行的序号
+}
+
+impl LogBuffer {
+    fn push(&mut self, record: LogRecord) {
+        self.records.push_back(record);
+        self.next += 1;
+        if self.records.len() > MAX_LOG_LINES {
+            self.records.pop_front();
+        }
+    }
+
+    // 返回序号since之后的日志以及下一次读取的序号
+    fn since(&self, since: u64) -> (Vec<LogRecord>, u64) {
+        let first = self.next - self.records.len() as u64;
+        let skip = since.saturating_sub(first) as usize;
+        (self.records.iter().skip(skip).cloned().collect(), self.next)
+    }
+}
+
+struct Daemon {
+    state: Mutex<AppState>,
+    logs: Mutex<LogBuffer>,
+}
+
+impl Daemon {
+    fn log(&self, record: LogRecord) {
+        eprintln!("{}", record);
+        if let Ok(mut logs) = self.logs.lock() {
+            logs.push(record);
+        }
+    }
+
+    fn status(state: &AppState) -> Value {
+        json!({
+            "pid": std::process::id(),
+            "profile": state.settings.active_profile,
+            "base_dir": state.replay_dir,
+            "running": state.watcher_running,
+            "paused": state.watcher_paused,
+            "dirs": state.monitor_instance.as_ref().map(|i| i.dirs()).unwrap_or_default(),
+            "snapshot": state.monitor_instance.as_ref().map(|i| i.snapshot().to_json()),
+        })
+    }
+
+    fn dispatch(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if method == "logs" {
+            let since = params.get("since").and_then(|v| v.as_u64()).unwrap_or(0);
+            let (records, next) = self.logs.lock().map(|l| l.since(since)).unwrap_or_default();
+            return Ok(json!({ "records": records, "next": next }));
+        }
+
+        let mut state = self.state.lock().map_err(|_| (SERVER_ERROR, "状态锁已损坏".to_string()))?;
+        match method {
+            "status" => Ok(Self::status(&state)),
+            "start" => {
+                if !state.watcher_running {
+                    state.start_monitor();
+                }
+                Ok(Self::status(&state))
+            }
+            "stop" => {
+                if state.watcher_running {
+                    state.stop_monitor();
+                }
+                Ok(Self::status(&state))
+            }
+            "pause" | "resume" => {
+                state.set_watcher_paused(method == "pause");
+                Ok(Self::status(&state))
+            }
+            "batch" => match params.get("dirs") {
+                Some(dirs) => serde_json::from_value::<Vec<PathBuf>>(dirs.clone())
+                    .map_err(|e| (INVALID_PARAMS, format!("dirs无效: {}", e)))
+                    .map(|dirs| {
+                        state.run_batch(dirs.clone());
+                        json!({ "dirs": dirs })
+                    }),
+                None => {
+                    let dirs = state.fix_dirs();
+                    state.run_batch(dirs.clone());
+                    Ok(json!({ "dirs": dirs }))
+                }
+            },
+            "reload" => match load_settings() {
+                Ok(mut settings) => {
+                    let profile = params
+                        .get("profile")
+                        .and_then(|p| p.as_str())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| state.settings.active_profile.clone());
+                    settings.select_profile(&profile);
+                    state.apply_settings(settings);
+                    Ok(Self::status(&state))
+                }
+                Err(e) => Err((SERVER_ERROR, format!("{:#}", e))),
+            },
+            "shutdown" => {
+                if state.watcher_running {
+                    state.stop_monitor();
+                }
+                Ok(json!({ "shutdown": true }))
+            }
+            other => Err((METHOD_NOT_FOUND, format!("未知方法: {}", other))),
+        }
+    }
+}
+
+// 以守护进程方式运行：启动监控并等待控制连接，直到收到shutdown
+// events为启动前订阅的事件，在加载设置之前订阅才不会漏掉加载过程中的日志
+pub fn run(mut state: AppState, events: Receiver<Event>, watch: bool) -> anyhow::Result<()> {
+    if DaemonClient::connect().is_some() {
+        return Err(anyhow::anyhow!("守护进程已在运行: {}", endpoint_path().display()));
+    }
+
+    let server = ipc::bind(ENDPOINT)?;
+    state.bus.log(
+        LogRecord::new(LogLevel::Info, LogSource::Monitor, "守护进程已启动，控制接口").with_path(&endpoint_path()),
+    );
+
+    if watch {
+        state.start_monitor();
+    }
+    let daemon = Arc::new(Daemon {
+        state: Mutex::new(state),
+        logs: Mutex::new(LogBuffer::default()),
+    });
+
+    // 日志进入缓冲，其他事件（如自动发现的新目录）交给状态处理
+    let pump = daemon.clone();
+    std::thread::spawn(move || {
+        for event in events.iter() {
+            match event {
+                Event::Log(record) => pump.log(record),
+                other => {
+                    if let Ok(mut state) = pump.state.lock() {
+                        state.handle_event(other);
+                    }
+                }
+            }
+        }
+    });
+
+    let token = Arc::new(server.token);
+    for stream in server.listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let Ok(reader) = stream.try_clone() else { continue };
+        let daemon = daemon.clone();
+        let token = token.clone();
+
+        std::thread::spawn(move || {
+            // 回复shutdown请求后再退出
+            let mut shutdown = false;
+            ipc::handle_requests(BufReader::new(reader), stream, token.as_deref(), |method, params| {
+                shutdown |= method == "shutdown";
+                daemon.dispatch(method, params)
+            });
+            if shutdown {
+                daemon.log(LogRecord::new(LogLevel::Info, LogSource::Monitor, "🛑 守护进程已退出"));
+                ipc::remove_endpoint(ENDPOINT);
+                std::process::exit(0);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::PARSE_ERROR;
+
+    #[test]
+    fn test_log_buffer_since() {
+        let mut logs = LogBuffer::default();
+        for i in 0..MAX_LOG_LINES + 5 {
+            logs.push(LogRecord::new(LogLevel::Info, LogSource::App, i.to_string()));
+        }
+
+        let (records, next) = logs.since(0);
+        assert_eq!(records.len(), MAX_LOG_LINES);
+        assert_eq!(records[0].message, "5");
+        assert_eq!(next, (MAX_LOG_LINES + 5) as u64);
+
+        let (records, _) = logs.since(next - 2);
+        let messages: Vec<String> = records.into_iter().map(|r| r.message).collect();
+        assert_eq!(messages, vec![(MAX_LOG_LINES + 3).to_string(), (MAX_LOG_LINES + 4).to_string()]);
+    }
+
+    #[test]
+    fn test_handle_connection() {
+        let daemon = Daemon {
+            state: Mutex::new(AppState::default()),
+            logs: Mutex::new(LogBuffer::default()),
+        };
+        let input = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"status\"}\n{\"id\":2,\"method\":\"nope\"}\nnot json\n";
+        let mut output = Vec::new();
+
+        ipc::handle_requests(input.as_bytes(), &mut output, None, |method, params| daemon.dispatch(method, params));
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["result"]["running"], json!(false));
+        assert_eq!(responses[1]["error"]["code"], json!(METHOD_NOT_FOUND));
+        assert_eq!(responses[2]["error"]["code"], json!(PARSE_ERROR));
+    }
+}
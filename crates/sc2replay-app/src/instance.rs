@@ -0,0 +1,46 @@
+use serde_json::json;
+
+use crate::ipc::{self, METHOD_NOT_FOUND};
+
+/*
+    单实例锁：图形界面启动时锁定并监听instance端点，锁在进程退出前一直持有
+    再次启动时连接已有实例，通知它显示窗口后退出，避免两个监控同时修复同一个录像
+*/
+
+const ENDPOINT: &str = "instance";
+
+// 已有实例在运行时返回其进程号
+pub fn running_pid() -> Option<u64> {
+    ipc::call(ENDPOINT, 1, "status", json!({}))
+        .ok()
+        .and_then(|r| r.get("pid").and_then(|p| p.as_u64()))
+}
+
+// 通知已有实例显示窗口，返回其进程号
+pub fn notify_existing() -> Option<u64> {
+    ipc::call(ENDPOINT, 1, "show", json!({}))
+        .ok()
+        .and_then(|r| r.get("pid").and_then(|p| p.as_u64()))
+}
+
+// 成为唯一实例，之后其他实例要求显示窗口时调用on_show；已有实例时返回错误
+pub fn acquire(on_show: impl Fn() + Send + Sync + 'static) -> anyhow::Result<()> {
+    let server = ipc::bind(ENDPOINT)?;
+    ipc::serve(server, move |method, _params| {
+        let pid = json!({ "pid": std::process::id() });
+        match method {
+            "status" => Ok(pid),
+            "show" => {
+                on_show();
+                Ok(pid)
+            }
+            other => Err((METHOD_NOT_FOUND, format!("未知方法: {}", other))),
+        }
+    });
+    Ok(())
+}
+
+// 退出时删除端点，异常退出时锁随进程释放，留下的端点会在下次启动时被替换
+pub fn release() {
+    ipc::remove_endpoint(ENDPOINT);
+}
@@ -0,0 +1,269 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::Context;
+use serde_json::{json, Value};
+
+/*
+    本地进程间通信：每行一个JSON-RPC 2.0请求/响应
+    Unix上使用域套接字，位于只有当前用户能访问的运行时目录；
+    其他平台使用仅监听127.0.0.1的TCP端口，本机的其他用户也能连接，因此启动时生成随机令牌，
+    与端口号一起写入当前用户配置目录中的<名称>.port（"端口 令牌"），每个请求都必须在token字段中携带该令牌
+    监听前先锁定<名称>.lock，锁在进程退出前一直持有，同一端点只能有一个进程监听
+*/
+
+#[cfg(unix)]
+pub type Stream = std::os::unix::net::UnixStream;
+#[cfg(not(unix))]
+pub type Stream = std::net::TcpStream;
+
+#[cfg(unix)]
+pub type Listener = std::os::unix::net::UnixListener;
+#[cfg(not(unix))]
+pub type Listener = std::net::TcpListener;
+
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// JSON-RPC错误码
+pub const PARSE_ERROR: i64 = -32700;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const SERVER_ERROR: i64 = -32000;
+pub const UNAUTHORIZED: i64 = -32001;
+
+// 通信端点位置：Unix为套接字文件，其他平台为记录端口号的文件
+pub fn endpoint_path(name: &str) -> PathBuf {
+    let dir = dirs::runtime_dir()
+        .filter(|_| cfg!(unix))
+        .or_else(dirs::config_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("sc2replay-autofix");
+    if cfg!(unix) { dir.join(format!("{}.sock", name)) } else { dir.join(format!("{}.port", name)) }
+}
+
+// 连接到端点，返回连接和请求需要携带的令牌
+pub fn connect(name: &str) -> anyhow::Result<(Stream, Option<String>)> {
+    let endpoint = endpoint_path(name);
+
+    #[cfg(unix)]
+    let (stream, token) = (Stream::connect(&endpoint)?, None);
+    #[cfg(not(unix))]
+    let (stream, token) = {
+        let content = std::fs::read_to_string(&endpoint)?;
+        let (port, token) = content.trim().split_once(' ').context("端口文件格式错误")?;
+        (Stream::connect(("127.0.0.1", port.parse::<u16>()?))?, Some(token.to_string()))
+    };
+
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    Ok((stream, token))
+}
+
+// 监听端点，token为请求必须携带的令牌
+pub struct Server {
+    pub listener: Listener,
+    pub token: Option<String>,
+    // 端点的锁，随Server一起释放
+    _lock: File,
+}
+
+// 锁定端点的锁文件，已被其他进程锁定时返回错误
+fn lock_endpoint(name: &str) -> anyhow::Result<File> {
+    let path = endpoint_path(name).with_extension("lock");
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("无法打开锁文件: {}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(std::fs::TryLockError::WouldBlock) => Err(anyhow::anyhow!("已有其他进程在使用{}", path.display())),
+        Err(std::fs::TryLockError::Error(e)) => Err(e).with_context(|| format!("无法锁定: {}", path.display())),
+    }
+}
+
+// 32字节随机数的十六进制
+#[cfg(not(unix))]
+fn new_token() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("无法生成令牌: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// 锁定并创建监听端点，已有其他进程在监听时返回错误
+pub fn bind(name: &str) -> anyhow::Result<Server> {
+    let endpoint = endpoint_path(name);
+    if let Some(parent) = endpoint.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let lock = lock_endpoint(name)?;
+
+    // 持有锁时已有的套接字文件只可能是旧进程异常退出时留下的
+    #[cfg(unix)]
+    let server = {
+        let _ = std::fs::remove_file(&endpoint);
+        let listener = Listener::bind(&endpoint).with_context(|| format!("无法创建控制套接字: {}", endpoint.display()))?;
+        Server { listener, token: None, _lock: lock }
+    };
+    #[cfg(not(unix))]
+    let server = {
+        let listener = Listener::bind(("127.0.0.1", 0))?;
+        let token = new_token()?;
+        std::fs::write(&endpoint, format!("{} {}", listener.local_addr()?.port(), token))
+            .with_context(|| format!("无法写入端口文件: {}", endpoint.display()))?;
+        Server { listener, token: Some(token), _lock: lock }
+    };
+
+    Ok(server)
+}
+
+// 进程退出时删除端点文件，锁文件保留
+pub fn remove_endpoint(name: &str) {
+    let _ = std::fs::remove_file(endpoint_path(name));
+}
+
+// 发送一个请求并等待响应
+pub fn call(name: &str, id: u64, method: &str, params: Value) -> anyhow::Result<Value> {
+    let (mut stream, token) = connect(name)?;
+    let mut request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    if let Some(token) = token {
+        request["token"] = json!(token);
+    }
+    writeln!(stream, "{}", request)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: Value = serde_json::from_str(&line).context("响应格式错误")?;
+
+    if let Some(error) = response.get("error") {
+        let message = error.get("message").and_then(|m| m.as_str()).unwrap_or("未知错误");
+        return Err(anyhow::anyhow!("{}", message));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+// 逐字节比较，耗时与令牌内容无关
+fn token_matches(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected.bytes().zip(actual.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+// 处理一个连接上的所有请求，handler返回结果或(错误码, 错误信息)
+// token不为空时，没有携带相同令牌的请求直接返回错误，不交给handler
+pub fn handle_requests(
+    reader: impl BufRead,
+    mut writer: impl Write,
+    token: Option<&str>,
+    mut handler: impl FnMut(&str, &Value) -> Result<Value, (i64, String)>,
+) {
+    for line in reader.lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => {
+                let id = request.get("id").cloned().unwrap_or(Value::Null);
+                let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
+                let params = request.get("params").cloned().unwrap_or(json!({}));
+                let authorized = token.is_none_or(|expected| {
+                    request.get("token").and_then(|t| t.as_str()).is_some_and(|t| token_matches(expected, t))
+                });
+                let result = if authorized {
+                    handler(method, &params)
+                } else {
+                    Err((UNAUTHORIZED, "令牌无效".to_string()))
+                };
+                match result {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message }
+                    }),
+                }
+            }
+            Err(e) => json!({
+                "jsonrpc": "2.0", "id": null,
+                "error": { "code": PARSE_ERROR, "message": format!("请求格式错误: {}", e) }
+            }),
+        };
+
+        if writeln!(writer, "{}", response).is_err() {
+            break;
+        }
+    }
+}
+
+// 在后台线程中接受连接，每个连接一个线程
+pub fn serve<H>(server: Server, handler: H)
+where
+    H: Fn(&str, &Value) -> Result<Value, (i64, String)> + Send + Sync + 'static,
+{
+    let handler = std::sync::Arc::new(handler);
+    let token = std::sync::Arc::new(server.token);
+    std::thread::spawn(move || {
+        for stream in server.listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let Ok(reader) = stream.try_clone() else { continue };
+            let handler = handler.clone();
+            let token = token.clone();
+            std::thread::spawn(move || {
+                handle_requests(BufReader::new(reader), stream, token.as_deref(), |method, params| handler(method, params));
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_round_trip() {
+        let name = format!("test-{}", std::process::id());
+        assert!(call(&name, 1, "ping", json!({})).is_err());
+
+        serve(bind(&name).unwrap(), |method, params| match method {
+            "echo" => Ok(params.clone()),
+            other => Err((METHOD_NOT_FOUND, format!("未知方法: {}", other))),
+        });
+
+        assert_eq!(call(&name, 1, "echo", json!({ "a": 1 })).unwrap(), json!({ "a": 1 }));
+        assert!(call(&name, 2, "ping", json!({})).is_err());
+        remove_endpoint(&name);
+    }
+
+    #[test]
+    fn test_bind_locked() {
+        let name = format!("test-lock-{}", std::process::id());
+        let server = bind(&name).unwrap();
+        // 已在监听时不能再次创建，也不会删掉正在使用的端点
+        assert!(bind(&name).is_err());
+        assert!(endpoint_path(&name).exists());
+
+        drop(server);
+        assert!(bind(&name).is_ok());
+        remove_endpoint(&name);
+        std::fs::remove_file(endpoint_path(&name).with_extension("lock")).ok();
+    }
+
+    #[test]
+    fn test_handle_requests_token() {
+        let input = "{\"id\":1,\"method\":\"echo\",\"token\":\"secret\"}\n{\"id\":2,\"method\":\"echo\",\"token\":\"secrex\"}\n{\"id\":3,\"method\":\"echo\"}\n";
+        let mut output = Vec::new();
+        let mut calls = 0;
+        handle_requests(input.as_bytes(), &mut output, Some("secret"), |_, _| {
+            calls += 1;
+            Ok(json!(true))
+        });
+
+        let responses: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(responses[0]["result"], json!(true));
+        assert_eq!(responses[1]["error"]["code"], json!(UNAUTHORIZED));
+        assert_eq!(responses[2]["error"]["code"], json!(UNAUTHORIZED));
+        // 令牌不对的请求不交给handler
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,13 @@
+//! 图形界面和命令行共用的部分：设置和配置文件、应用状态、守护进程及其控制接口
+//!
+//! 录像解析、修复和监控在sc2replay-core中，这里只负责把它们组织成应用。
+//! 前端创建一个事件总线放进[`message::AppState`]，订阅后把收到的事件交给`AppState::handle_event`。
+
+pub mod autostart;
+pub mod cli;
+pub mod daemon;
+pub mod instance;
+pub mod ipc;
+pub mod logging;
+pub mod message;
+pub mod settings;
@@ -0,0 +1,236 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+
+use sc2replay_core::events::{Event, EventBus};
+use sc2replay_core::log::LogRecord;
+
+use crate::settings::LogSettings;
+
+/*
+    日志文件：写在应用数据目录的logs下，每行一条LogRecord的JSON
+    图形界面、守护进程和命令行各写各的文件(gui.jsonl、daemon.jsonl、cli.jsonl)，避免两个进程同时轮转同一个文件
+    当前文件超过大小上限时轮转：gui.jsonl -> gui.1.jsonl -> gui.2.jsonl ...，超出保留数量的最旧文件被删除
+*/
+
+fn data_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|d| d.join("sc2replay-autofix"))
+}
+
+pub fn log_dir() -> Option<PathBuf> {
+    data_dir().map(|d| d.join("logs"))
+}
+
+// 修复记录文件，所有前端共用一个，见sc2replay_core::journal
+pub fn journal_path() -> Option<PathBuf> {
+    data_dir().map(|d| d.join("journal.jsonl"))
+}
+
+// 第index个文件，0为当前文件
+fn log_file_path(dir: &Path, name: &str, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join(format!("{}.jsonl", name))
+    } else {
+        dir.join(format!("{}.{}.jsonl", name, index))
+    }
+}
+
+// 某个前端已有的日志文件，从旧到新
+pub fn log_files(dir: &Path, name: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut index = 0;
+    loop {
+        let path = log_file_path(dir, name, index);
+        if !path.exists() {
+            break;
+        }
+        files.push(path);
+        index += 1;
+    }
+    files.reverse();
+    files
+}
+
+// 读取某个前端从since_ms起的日志，按时间顺序；无法解析的行被跳过
+pub fn read_records(dir: &Path, name: &str, since_ms: u64) -> anyhow::Result<Vec<LogRecord>> {
+    let mut records = Vec::new();
+    for path in log_files(dir, name) {
+        let content = fs::read_to_string(&path).with_context(|| format!("无法读取日志文件: {}", path.display()))?;
+        records.extend(
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<LogRecord>(line).ok())
+                .filter(|r| r.timestamp_ms >= since_ms),
+        );
+    }
+    Ok(records)
+}
+
+// 导出日志，扩展名为.json时导出JSON数组，否则每行一条文本
+pub fn export_records(records: &[LogRecord], path: &Path) -> anyhow::Result<()> {
+    let content = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        serde_json::to_string_pretty(records)?
+    } else {
+        records.iter().map(|r| format!("{}\n", r)).collect()
+    };
+    fs::write(path, content).with_context(|| format!("无法写入文件: {}", path.display()))
+}
+
+pub struct LogWriter {
+    dir: PathBuf,
+    name: String,
+    settings: LogSettings,
+    file: File,
+    size: u64,
+}
+
+impl LogWriter {
+    pub fn open(dir: &Path, name: &str, settings: LogSettings) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("无法创建日志目录: {}", dir.display()))?;
+        let path = log_file_path(dir, name, 0);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("无法打开日志文件: {}", path.display()))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            name: name.to_string(),
+            settings,
+            file,
+            size,
+        })
+    }
+
+    pub fn path(&self) -> PathBuf {
+        log_file_path(&self.dir, &self.name, 0)
+    }
+
+    // 写入一条记录，低于设置级别的记录被忽略
+    pub fn write(&mut self, record: &LogRecord) -> anyhow::Result<()> {
+        if record.level < self.settings.level {
+            return Ok(());
+        }
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.settings.max_file_kb.max(1) * 1024 {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        let keep = self.settings.max_files.max(1);
+        fs::remove_file(log_file_path(&self.dir, &self.name, keep - 1)).ok();
+        for index in (0..keep - 1).rev() {
+            let from = log_file_path(&self.dir, &self.name, index);
+            if from.exists() {
+                fs::rename(&from, log_file_path(&self.dir, &self.name, index + 1))
+                    .with_context(|| format!("无法轮转日志文件: {}", from.display()))?;
+            }
+        }
+
+        let path = self.path();
+        self.file = File::create(&path).with_context(|| format!("无法创建日志文件: {}", path.display()))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+// 订阅事件总线，把日志写入name对应的文件，返回当前日志文件路径
+pub fn spawn_file_logger(bus: &EventBus, name: &str, settings: &LogSettings) -> anyhow::Result<Option<PathBuf>> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let dir = log_dir().ok_or_else(|| anyhow::anyhow!("无法确定日志目录"))?;
+    let mut writer = LogWriter::open(&dir, name, settings.clone())?;
+    let path = writer.path();
+
+    let events = bus.subscribe();
+    std::thread::Builder::new()
+        .name("log-writer".to_string())
+        .spawn(move || {
+            for event in events.iter() {
+                if let Event::Log(record) = event
+                    && let Err(e) = writer.write(&record)
+                {
+                    eprintln!("[失败]写入日志文件失败: {:#}", e);
+                }
+            }
+        })?;
+
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sc2replay_core::log::{LogLevel, LogSource};
+
+    #[test]
+    fn test_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-log-rotation-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+
+        let settings = LogSettings {
+            enabled: true,
+            level: LogLevel::Info,
+            max_file_kb: 1,
+            max_files: 3,
+        };
+        let mut writer = LogWriter::open(&dir, "test", settings).unwrap();
+        let record = LogRecord::new(LogLevel::Info, LogSource::App, "x".repeat(300));
+        for _ in 0..20 {
+            writer.write(&record).unwrap();
+        }
+        // 低于设置级别的记录不写入
+        writer.write(&LogRecord::new(LogLevel::Debug, LogSource::App, "调试")).unwrap();
+
+        let files = log_files(&dir, "test");
+        assert_eq!(files.len(), 3);
+        assert_eq!(files.last(), Some(&writer.path()));
+        for file in &files {
+            let content = fs::read_to_string(file).unwrap();
+            assert!(content.len() <= 1024);
+            for line in content.lines() {
+                assert_eq!(serde_json::from_str::<LogRecord>(line).unwrap(), record);
+            }
+        }
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_read_and_export_records() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-log-export-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+
+        let mut old = LogRecord::new(LogLevel::Info, LogSource::App, "上次运行");
+        old.timestamp_ms = 1000;
+        let current = LogRecord::new(LogLevel::Error, LogSource::Fixer, "修复失败").with_path(Path::new("a.SC2Replay"));
+        let mut writer = LogWriter::open(&dir, "test", LogSettings::default()).unwrap();
+        writer.write(&old).unwrap();
+        writer.write(&current).unwrap();
+        fs::write(log_file_path(&dir, "test", 1), "不是JSON\n").unwrap();
+
+        let records = read_records(&dir, "test", 2000).unwrap();
+        assert_eq!(records, vec![current.clone()]);
+
+        let json_path = dir.join("export.json");
+        export_records(&records, &json_path).unwrap();
+        let exported: Vec<LogRecord> = serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(exported, records);
+
+        let text_path = dir.join("export.txt");
+        export_records(&records, &text_path).unwrap();
+        assert_eq!(fs::read_to_string(&text_path).unwrap(), format!("{}\n", current));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use sc2replay_core::discovery::{discover_replay_locations, read_player_name, ReplayCategories, ReplayLocation};
+use sc2replay_core::events::{DirectoryChange, Event, EventBus};
+use sc2replay_core::fixer::{batch_fix_dirs, FixOptions};
+use sc2replay_core::log::{LogLevel, LogRecord, LogSource};
+use sc2replay_core::monitor::{start_watch_multiple, MonitorSnapshot};
+
+use crate::daemon::DaemonClient;
+use crate::settings::Settings;
+
+// 单个账户的别名以及修复/监控开关
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountOptions {
+    pub alias: String,
+    pub fix: bool,
+    pub monitor: bool,
+}
+
+impl Default for AccountOptions {
+    fn default() -> Self {
+        Self {
+            alias: String::new(),
+            fix: true,
+            monitor: true,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct AppState {
+    pub replay_dir: PathBuf,
+    pub all_replay_dirs: Vec<PathBuf>, // 存储所有找到的Replays目录
+    pub replay_categories: ReplayCategories,
+    pub replay_locations: Vec<ReplayLocation>, // 各账户下找到的所有类型目录
+    pub account_options: HashMap<PathBuf, AccountOptions>, // 键为账户目录
+    pub player_names: HashMap<PathBuf, String>, // 从最新录像读取的玩家名
+    pub auto_fix: bool,
+    pub auto_start: bool,
+    pub watcher_running: bool,
+    pub watcher_paused: bool,
+    pub monitor_instance: Option<sc2replay_core::monitor::MonitorInstance>,
+    pub settings: Settings, // 输出方式、规则文件等未单独存放的设置
+    pub fix_options: FixOptions, // 由settings得到的修复选项，批量修复和监控使用
+    pub daemon: Option<DaemonClient>, // 已连接的后台守护进程，连接后监控由守护进程负责
+    pub daemon_snapshot: Option<MonitorSnapshot>,
+    pub daemon_log_cursor: u64,
+    pub log: VecDeque<LogRecord>,
+    pub log_dropped: u64, // 超出上限被丢弃的日志条数，log[i]是第log_dropped + i条
+    pub bus: EventBus, // 修复和监控通过它报告日志和状态，前端订阅后交给handle_event
+    pub batch_progress: Option<(usize, usize)>, // 本地批量修复的进度(已完成, 总数)
+}
+
+impl AppState {
+    pub fn account_options(&self, account_dir: &Path) -> AccountOptions {
+        self.account_options.get(account_dir).cloned().unwrap_or_default()
+    }
+
+    // 账户显示名：别名 > 录像中的玩家名 > toon句柄 > 目录名
+    pub fn account_name(&self, location: &ReplayLocation) -> String {
+        let alias = self.account_options(&location.account_dir).alias;
+        if !alias.trim().is_empty() {
+            return alias.trim().to_string();
+        }
+        if let Some(name) = self.player_names.get(&location.account_dir) {
+            return name.clone();
+        }
+        if let Some(handle) = &location.toon_handle {
+            return handle.clone();
+        }
+        location
+            .account_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string()
+    }
+
+    // 如 "Alice (CN) – Multiplayer"
+    pub fn location_label(&self, location: &ReplayLocation) -> String {
+        match location.region {
+            Some(region) => format!("{} ({}) – {}", self.account_name(location), region, location.category),
+            None => format!("{} – {}", self.account_name(location), location.category),
+        }
+    }
+
+    // 目录的显示名，手动添加的目录直接显示路径
+    pub fn dir_label(&self, dir: &Path) -> String {
+        match self.location_of(dir) {
+            Some(location) => self.location_label(location),
+            None => dir.to_str().unwrap_or("").to_string(),
+        }
+    }
+
+    fn location_of(&self, dir: &Path) -> Option<&ReplayLocation> {
+        self.replay_locations.iter().find(|l| l.path == dir)
+    }
+
+    fn dir_enabled(&self, dir: &Path, monitor: bool) -> bool {
+        match self.location_of(dir) {
+            Some(location) => {
+                let options = self.account_options(&location.account_dir);
+                if monitor { options.monitor } else { options.fix }
+            }
+            None => true,
+        }
+    }
+
+    // 批量修复使用的目录
+    pub fn fix_dirs(&self) -> Vec<PathBuf> {
+        self.all_replay_dirs.iter().filter(|d| self.dir_enabled(d, false)).cloned().collect()
+    }
+
+    // 监控使用的目录
+    pub fn monitor_dirs(&self) -> Vec<PathBuf> {
+        self.all_replay_dirs.iter().filter(|d| self.dir_enabled(d, true)).cloned().collect()
+    }
+
+    // 监控开关关闭的目录，启动监控后需排除，避免被自动发现重新加入
+    pub fn monitor_excluded_dirs(&self) -> Vec<PathBuf> {
+        self.replay_locations
+            .iter()
+            .filter(|l| !self.account_options(&l.account_dir).monitor)
+            .map(|l| l.path.clone())
+            .collect()
+    }
+
+    pub fn set_account_options(&mut self, account_dir: &Path, options: AccountOptions) {
+        let previous = self.account_options(account_dir);
+        self.account_options.insert(account_dir.to_path_buf(), options.clone());
+
+        // 监控运行中则同步该账户的监控目录
+        if previous.monitor != options.monitor
+            && let Some(instance) = &self.monitor_instance
+        {
+            for location in self.replay_locations.iter().filter(|l| l.account_dir == account_dir) {
+                if options.monitor {
+                    if self.all_replay_dirs.contains(&location.path) {
+                        let _ = instance.add_dir(location.path.clone());
+                    }
+                } else {
+                    instance.remove_dir(&location.path);
+                }
+            }
+        }
+    }
+
+    // 启动多目录监控
+    pub fn start_monitor(&mut self) {
+        if self.daemon.is_some() {
+            self.call_daemon("start", serde_json::json!({}));
+            return;
+        }
+
+        let dirs = self.monitor_dirs();
+        if dirs.is_empty() {
+            self.bus.error(LogSource::Monitor, "❌ 未找到任何录像目录");
+            return;
+        }
+
+        match start_watch_multiple(dirs, self.fix_options.clone(), &self.bus) {
+            Ok(instance) => {
+                instance.set_quiet_period(Duration::from_millis(self.settings.monitor.quiet_period_ms));
+
+                // 关闭了监控的账户不参与自动发现
+                for dir in self.monitor_excluded_dirs() {
+                    instance.remove_dir(&dir);
+                }
+
+                // 同时监控Accounts目录，新账户或新赛季目录会自动加入
+                if self.settings.monitor.watch_accounts {
+                    let base_dir = self.replay_dir.clone();
+                    let categories = self.replay_categories.clone();
+                    if let Err(e) = instance.watch_accounts(base_dir, categories) {
+                        self.bus.error(LogSource::Discovery, format!("账户目录监控启动失败: {}", e));
+                    }
+                }
+
+                self.monitor_instance = Some(instance);
+                self.watcher_running = true;
+                self.bus.info(LogSource::Monitor, "多目录监控启动成功");
+            }
+            Err(e) => {
+                self.bus.error(LogSource::Monitor, format!("监控启动失败: {}", e));
+                self.watcher_running = false;
+            }
+        }
+    }
+
+    pub fn stop_monitor(&mut self) {
+        if self.daemon.is_some() {
+            self.call_daemon("stop", serde_json::json!({}));
+            return;
+        }
+
+        if let Some(instance) = &self.monitor_instance {
+            instance.stop();
+        }
+        self.watcher_running = false;
+        self.watcher_paused = false;
+        self.monitor_instance = None;
+        self.bus.info(LogSource::Monitor, "🛑 监控已停止，禁止新任务创建");
+    }
+
+    // 批量修复，连接守护进程时由守护进程执行
+    pub fn run_batch(&mut self, dirs: Vec<PathBuf>) {
+        if self.daemon.is_some() {
+            self.call_daemon("batch", serde_json::json!({ "dirs": dirs }));
+            return;
+        }
+
+        let bus = self.bus.clone();
+        let options = self.fix_options.clone();
+        std::thread::spawn(move || {
+            bus.info(LogSource::Fixer, "开始批量修复所有目录...");
+            if let Err(e) = batch_fix_dirs(&dirs, &options, &bus) {
+                bus.error(LogSource::Fixer, format!("批量修复失败: {}", e));
+            } else {
+                bus.info(LogSource::Fixer, "所有目录修复完成");
+            }
+        });
+    }
+
+    // 调用守护进程并同步返回的监控状态，连接断开时改回本地监控
+    fn call_daemon(&mut self, method: &str, params: serde_json::Value) -> Option<serde_json::Value> {
+        let daemon = self.daemon.as_ref()?;
+        match daemon.call(method, params) {
+            Ok(result) => {
+                if let Some(running) = result.get("running").and_then(|v| v.as_bool()) {
+                    self.watcher_running = running;
+                    self.watcher_paused = result.get("paused").and_then(|v| v.as_bool()).unwrap_or(false);
+                    self.daemon_snapshot = result.get("snapshot").and_then(MonitorSnapshot::from_json);
+                }
+                Some(result)
+            }
+            Err(e) => {
+                self.bus.error(LogSource::Monitor, format!("与守护进程的连接已断开: {}", e));
+                self.daemon = None;
+                self.daemon_snapshot = None;
+                self.watcher_running = false;
+                self.watcher_paused = false;
+                None
+            }
+        }
+    }
+
+    // 定期从守护进程同步监控状态和新日志
+    pub fn poll_daemon(&mut self) {
+        if self.call_daemon("status", serde_json::json!({})).is_none() {
+            return;
+        }
+        let since = self.daemon_log_cursor;
+        if let Some(result) = self.call_daemon("logs", serde_json::json!({ "since": since })) {
+            // 守护进程的日志已由它自己写入文件，这里只放进内存
+            for record in result["records"].as_array().into_iter().flatten() {
+                if let Ok(mut record) = serde_json::from_value::<LogRecord>(record.clone()) {
+                    record.message = format!("[守护进程] {}", record.message);
+                    self.push_log(record);
+                }
+            }
+            self.daemon_log_cursor = result["next"].as_u64().unwrap_or(since);
+        }
+    }
+
+    // 应用设置中的当前配置：重新扫描目录并更新修复选项，监控运行中则在新目录上重启
+    pub fn apply_settings(&mut self, settings: Settings) {
+        let was_running = self.monitor_instance.is_some();
+        if was_running {
+            self.stop_monitor();
+        }
+
+        // 上一个配置的目录不再保留
+        self.all_replay_dirs.clear();
+        self.replay_locations.clear();
+        settings.apply_to(self);
+        self.rescan_replay_dirs();
+        settings.apply_extra_dirs(self);
+
+        self.load_fix_options(&settings);
+
+        if was_running {
+            self.bus.info(LogSource::Monitor, "配置已变更，正在重启监控");
+            self.start_monitor();
+        }
+    }
+
+    // 按设置更新修复选项，监控运行中则同时更新监控使用的选项；规则文件读取失败时记错误日志
+    pub fn load_fix_options(&mut self, settings: &Settings) {
+        let (fix_options, rule_errors) = settings.fix_options();
+        for e in rule_errors {
+            self.bus.error(LogSource::App, e.to_string());
+        }
+        if let Some(instance) = &self.monitor_instance {
+            instance.set_fix_options(fix_options.clone());
+        }
+        self.fix_options = fix_options;
+    }
+
+    // 切换配置，界面上未保存的修改先写回原配置
+    pub fn switch_profile(&mut self, name: &str) {
+        if name == self.settings.active_profile {
+            return;
+        }
+
+        let mut settings = Settings::from_state(self);
+        if settings.select_profile(name) {
+            self.bus.info(LogSource::App, format!("已新建配置: {}", name));
+        }
+        self.apply_settings(settings);
+        self.bus.info(LogSource::App, format!(
+            "已切换到配置: {}，共{}个录像目录",
+            name,
+            self.all_replay_dirs.len()
+        ));
+    }
+
+    // 删除当前配置并切换到剩余的第一个配置
+    pub fn remove_active_profile(&mut self) {
+        let name = self.settings.active_profile.clone();
+        let mut settings = self.settings.clone();
+        if !settings.remove_profile(&name) {
+            self.bus.error(LogSource::App, "不能删除最后一个配置");
+            return;
+        }
+
+        self.bus.info(LogSource::App, format!("已删除配置: {}", name));
+        self.apply_settings(settings);
+        self.bus.info(LogSource::App, format!("已切换到配置: {}", self.settings.active_profile));
+    }
+
+    // 切换SC2基础目录：重新扫描录像目录，监控运行中则在新目录上重启
+    pub fn set_base_dir(&mut self, dir: PathBuf) {
+        if !dir.is_dir() {
+            self.bus.log(LogRecord::new(LogLevel::Error, LogSource::Discovery, "目录不存在").with_path(&dir));
+            return;
+        }
+        if dir == self.replay_dir {
+            return;
+        }
+
+        self.replay_dir = dir;
+        self.bus.log(LogRecord::new(LogLevel::Info, LogSource::Discovery, "📂 已选择目录").with_path(&self.replay_dir));
+
+        // 先停止旧监控，避免重新扫描时逐个同步目录
+        let was_running = self.monitor_instance.is_some();
+        if was_running {
+            self.stop_monitor();
+        }
+
+        self.rescan_replay_dirs();
+        self.bus.info(LogSource::Discovery, format!("📂 重新扫描到{}个录像目录", self.all_replay_dirs.len()));
+
+        if was_running {
+            self.bus.info(LogSource::Monitor, "基础目录已变更，正在重启监控");
+            self.start_monitor();
+        }
+    }
+
+    // 按当前录像类型设置重新扫描目录，手动添加的目录保持不变
+    pub fn rescan_replay_dirs(&mut self) {
+        let locations = discover_replay_locations(&self.replay_dir, &self.replay_categories.categories);
+
+        let mut dirs: Vec<PathBuf> = self
+            .all_replay_dirs
+            .iter()
+            .filter(|d| !locations.iter().any(|l| &l.path == *d))
+            .filter(|d| !self.replay_locations.iter().any(|l| &l.path == *d))
+            .cloned()
+            .collect();
+        for location in &locations {
+            if self.replay_categories.is_enabled(&location.account_dir, &location.category) {
+                dirs.push(location.path.clone());
+            }
+        }
+
+        // 监控运行中则同步增删监控目录
+        if let Some(instance) = &self.monitor_instance {
+            instance.set_categories(self.replay_categories.clone());
+            for old in &self.all_replay_dirs {
+                if !dirs.contains(old) {
+                    instance.remove_dir(old);
+                }
+            }
+            for new in &dirs {
+                if !self.all_replay_dirs.contains(new)
+                    && self.dir_enabled(new, true)
+                    && let Err(e) = instance.add_dir(new.clone())
+                {
+                    self.bus.log(LogRecord::new(LogLevel::Error, LogSource::Monitor, format!("无法监控目录: {}", e)).with_path(new));
+                }
+            }
+        }
+
+        // 读取新账户的玩家名
+        for location in &locations {
+            if !self.player_names.contains_key(&location.account_dir)
+                && let Some(name) = read_player_name(location)
+            {
+                self.player_names.insert(location.account_dir.clone(), name);
+            }
+        }
+
+        self.replay_locations = locations;
+        self.all_replay_dirs = dirs;
+    }
+
+    pub fn add_replay_dir(&mut self, dir: PathBuf) {
+        if !self.all_replay_dirs.contains(&dir) {
+            self.bus.log(LogRecord::new(LogLevel::Info, LogSource::Discovery, "📂 已添加录像目录").with_path(&dir));
+            self.all_replay_dirs.push(dir.clone());
+        }
+
+        // 监控运行中则立即开始监控新目录
+        if let Some(instance) = &self.monitor_instance
+            && let Err(e) = instance.add_dir(dir.clone())
+        {
+            self.bus.log(LogRecord::new(LogLevel::Error, LogSource::Monitor, format!("无法监控新目录: {}", e)).with_path(&dir));
+        }
+    }
+
+    pub fn remove_replay_dir(&mut self, dir: &Path) {
+        self.all_replay_dirs.retain(|d| d != dir);
+        if let Some(instance) = &self.monitor_instance {
+            instance.remove_dir(dir);
+        }
+        self.bus.log(LogRecord::new(LogLevel::Info, LogSource::Discovery, "📂 已移除录像目录").with_path(dir));
+    }
+
+    pub fn add_rule_file(&mut self, path: PathBuf) {
+        let rule_files = &mut self.settings.profile_mut().rule_files;
+        if !rule_files.contains(&path) {
+            rule_files.push(path.clone());
+            self.bus.log(LogRecord::new(LogLevel::Info, LogSource::App, "📂 已添加规则文件").with_path(&path));
+        }
+    }
+
+    // 暂停或恢复监控，连接守护进程时转发给守护进程
+    pub fn set_watcher_paused(&mut self, paused: bool) {
+        if self.daemon.is_some() {
+            self.call_daemon(if paused { "pause" } else { "resume" }, serde_json::json!({}));
+        } else if let Some(instance) = &self.monitor_instance {
+            if paused {
+                instance.pause();
+            } else {
+                instance.resume();
+            }
+            self.watcher_paused = paused;
+        }
+    }
+
+    // 追加一条日志，超出上限时丢弃最早的
+    fn push_log(&mut self, record: LogRecord) {
+        self.log.push_back(record);
+        if self.log.len() > self.settings.ui.max_log_lines.max(1) {
+            self.log.pop_front();
+            self.log_dropped += 1;
+        }
+    }
+
+    // 处理从事件总线收到的事件
+    pub fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Log(record) => self.push_log(record),
+            Event::DirectoriesChanged(DirectoryChange::BaseDir(dir)) => self.set_base_dir(dir),
+            Event::DirectoriesChanged(DirectoryChange::Added(dir)) => self.add_replay_dir(dir),
+            Event::DirectoriesChanged(DirectoryChange::Removed(dir)) => self.remove_replay_dir(&dir),
+            Event::MonitorStateChanged { running, paused } => {
+                // 连接守护进程时以守护进程的状态为准
+                if self.daemon.is_none() {
+                    self.watcher_running = running;
+                    self.watcher_paused = paused;
+                }
+            }
+            Event::Progress { done, total } => {
+                self.batch_progress = Some((done, total));
+            }
+            Event::FixStarted(_) | Event::FixCompleted { .. } => {}
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_set_base_dir_rescans() {
+        let base_dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-base-dir-{}", std::process::id()));
+        fs::remove_dir_all(&base_dir).ok();
+        let replays = base_dir
+            .join("Accounts")
+            .join("1")
+            .join("5-S2-1-1")
+            .join("Replays")
+            .join("Multiplayer");
+        fs::create_dir_all(&replays).unwrap();
+
+        let mut state = AppState::default();
+        state.set_base_dir(base_dir.join("missing"));
+        assert_eq!(state.replay_dir, PathBuf::new());
+
+        state.set_base_dir(base_dir.clone());
+        assert_eq!(state.replay_dir, base_dir);
+        assert_eq!(state.all_replay_dirs, vec![replays]);
+
+        fs::remove_dir_all(&base_dir).ok();
+    }
+
+    #[test]
+    fn test_switch_profile_keeps_dirs_per_profile() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-profile-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut state = AppState::default();
+        state.all_replay_dirs.push(dir.clone());
+
+        state.switch_profile("caster");
+        assert_eq!(state.settings.active_profile, "caster");
+        assert!(state.all_replay_dirs.is_empty());
+
+        state.switch_profile(crate::settings::DEFAULT_PROFILE);
+        assert_eq!(state.all_replay_dirs, vec![dir.clone()]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_handle_event() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-handle-event-{}", std::process::id()));
+
+        let mut state = AppState::default();
+        state.handle_event(Event::DirectoriesChanged(DirectoryChange::Added(dir.clone())));
+        state.handle_event(Event::DirectoriesChanged(DirectoryChange::Added(dir.clone())));
+        assert_eq!(state.all_replay_dirs, vec![dir.clone()]);
+
+        state.handle_event(Event::Progress { done: 1, total: 3 });
+        assert_eq!(state.batch_progress, Some((1, 3)));
+
+        state.handle_event(Event::MonitorStateChanged { running: true, paused: true });
+        assert!(state.watcher_running && state.watcher_paused);
+
+        state.handle_event(Event::DirectoriesChanged(DirectoryChange::Removed(dir)));
+        assert!(state.all_replay_dirs.is_empty());
+    }
+}
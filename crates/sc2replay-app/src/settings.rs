@@ -0,0 +1,494 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use sc2replay_core::builds;
+use sc2replay_core::discovery::{default_replay_categories, ReplayCategory};
+use sc2replay_core::fixer::{FixOptions, OutputMode, DEFAULT_OUTPUT_TEMPLATE};
+use sc2replay_core::log::LogLevel;
+use sc2replay_core::monitor::DEFAULT_QUIET_PERIOD;
+use sc2replay_core::rules::load_rules;
+
+use crate::logging;
+use crate::message::{AccountOptions, AppState};
+
+/// 设置文件格式版本，结构变化时递增并在migrate中补充迁移步骤
+pub const SETTINGS_VERSION: u32 = 2;
+
+/// 默认配置名
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Profile>,
+    pub monitor: MonitorSettings,
+    pub ui: UiSettings,
+    pub autostart: AutostartSettings,
+    pub log: LogSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(DEFAULT_PROFILE.to_string(), Profile::default());
+        Self {
+            version: SETTINGS_VERSION,
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles,
+            monitor: MonitorSettings::default(),
+            ui: UiSettings::default(),
+            autostart: AutostartSettings::default(),
+            log: LogSettings::default(),
+        }
+    }
+}
+
+/// 一套配置：多人共用电脑时每位玩家/解说各用一套
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_dir: Option<PathBuf>,
+    pub extra_replay_dirs: Vec<PathBuf>, // 手动添加的录像目录
+    pub categories: Vec<ReplayCategory>,
+    pub accounts: Vec<AccountSetting>,
+    pub output: OutputSettings,
+    pub rule_files: Vec<PathBuf>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            base_dir: None,
+            extra_replay_dirs: Vec::new(),
+            categories: default_replay_categories(),
+            accounts: Vec::new(),
+            output: OutputSettings::default(),
+            rule_files: Vec::new(),
+        }
+    }
+}
+
+/// 单个账户的设置，键为账户目录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountSetting {
+    pub dir: PathBuf,
+    pub alias: String,
+    pub fix: bool,
+    pub monitor: bool,
+    /// 该账户单独启用的录像类型，不设置时使用默认类型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<String>>,
+}
+
+impl Default for AccountSetting {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            alias: String::new(),
+            fix: true,
+            monitor: true,
+            categories: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    pub mode: OutputMode,
+    pub template: String,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            mode: OutputMode::Suffix,
+            template: DEFAULT_OUTPUT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorSettings {
+    pub auto_fix: bool,
+    pub start_on_launch: bool, // 启动程序后自动开始监控
+    pub watch_accounts: bool,  // 自动发现新账户/新赛季目录
+    pub quiet_period_ms: u64,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        Self {
+            auto_fix: true,
+            start_on_launch: false,
+            watch_accounts: true,
+            quiet_period_ms: DEFAULT_QUIET_PERIOD.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    pub show_monitor_panel: bool,
+    pub max_log_lines: usize,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            show_monitor_panel: true,
+            max_log_lines: 200,
+        }
+    }
+}
+
+/// 开机自启动时的启动方式，是否启用由系统中的自启动项决定
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutostartSettings {
+    pub minimized: bool, // 启动后最小化窗口
+    pub headless: bool,  // 不打开窗口，以守护进程运行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>, // 启动时使用的配置
+}
+
+/// 日志文件，见logging模块
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    pub enabled: bool,
+    pub level: LogLevel,   // 低于该级别的日志不写入文件
+    pub max_file_kb: u64,  // 单个文件的大小上限，超过后轮转
+    pub max_files: usize,  // 保留的文件数，包括当前文件
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            level: LogLevel::Info,
+            max_file_kb: 1024,
+            max_files: 5,
+        }
+    }
+}
+
+impl Settings {
+    /// 当前使用的配置
+    pub fn profile(&self) -> Profile {
+        self.profiles.get(&self.active_profile).cloned().unwrap_or_default()
+    }
+
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        self.profiles.entry(self.active_profile.clone()).or_default()
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// 切换配置，不存在时按默认值新建，返回是否新建
+    pub fn select_profile(&mut self, name: &str) -> bool {
+        self.active_profile = name.to_string();
+        let created = !self.profiles.contains_key(name);
+        self.profile_mut();
+        created
+    }
+
+    /// 删除配置，不能删除最后一个配置
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        if self.profiles.len() <= 1 || self.profiles.remove(name).is_none() {
+            return false;
+        }
+        if self.active_profile == name {
+            self.active_profile = self.profiles.keys().next().cloned().unwrap_or_default();
+        }
+        true
+    }
+
+    /// 将当前配置应用到界面状态，之后需调用rescan_replay_dirs
+    pub fn apply_to(&self, state: &mut AppState) {
+        let profile = self.profile();
+        // 旧版本在未选择目录时保存了空路径，视为未设置
+        if let Some(base_dir) = profile.base_dir.as_ref().filter(|d| !d.as_os_str().is_empty()) {
+            state.replay_dir = base_dir.clone();
+        }
+        if !profile.categories.is_empty() {
+            state.replay_categories.categories = profile.categories.clone();
+        }
+
+        state.replay_categories.per_account.clear();
+        state.account_options.clear();
+        for account in &profile.accounts {
+            if let Some(categories) = &account.categories {
+                let enabled: HashSet<String> = categories.iter().cloned().collect();
+                state.replay_categories.per_account.insert(account.dir.clone(), enabled);
+            }
+            state.account_options.insert(
+                account.dir.clone(),
+                AccountOptions {
+                    alias: account.alias.clone(),
+                    fix: account.fix,
+                    monitor: account.monitor,
+                },
+            );
+        }
+
+        state.auto_fix = self.monitor.auto_fix;
+        state.settings = self.clone();
+    }
+
+    /// 加入手动添加的目录，需在rescan_replay_dirs之后调用
+    pub fn apply_extra_dirs(&self, state: &mut AppState) {
+        for dir in &self.profile().extra_replay_dirs {
+            if dir.exists() && !state.all_replay_dirs.contains(dir) {
+                state.all_replay_dirs.push(dir.clone());
+            }
+        }
+    }
+
+    /// 从当前界面状态生成设置，界面状态写入当前配置
+    pub fn from_state(state: &AppState) -> Self {
+        let mut account_dirs: Vec<&PathBuf> = state.account_options.keys().collect();
+        for dir in state.replay_categories.per_account.keys() {
+            if !account_dirs.contains(&dir) {
+                account_dirs.push(dir);
+            }
+        }
+        account_dirs.sort();
+
+        let accounts = account_dirs
+            .into_iter()
+            .map(|dir| {
+                let options = state.account_options(dir);
+                let categories = state.replay_categories.per_account.get(dir).map(|enabled| {
+                    let mut enabled: Vec<String> = enabled.iter().cloned().collect();
+                    enabled.sort();
+                    enabled
+                });
+                AccountSetting {
+                    dir: dir.clone(),
+                    alias: options.alias,
+                    fix: options.fix,
+                    monitor: options.monitor,
+                    categories,
+                }
+            })
+            .collect();
+
+        let extra_replay_dirs = state
+            .all_replay_dirs
+            .iter()
+            .filter(|d| !state.replay_locations.iter().any(|l| &l.path == *d))
+            .cloned()
+            .collect();
+
+        let mut settings = state.settings.clone();
+        settings.version = SETTINGS_VERSION;
+        settings.monitor.auto_fix = state.auto_fix;
+
+        let profile = settings.profile_mut();
+        // 还未选择目录时不保存空路径
+        profile.base_dir = Some(state.replay_dir.clone()).filter(|d| !d.as_os_str().is_empty());
+        profile.extra_replay_dirs = extra_replay_dirs;
+        profile.categories = state.replay_categories.categories.clone();
+        profile.accounts = accounts;
+        settings
+    }
+
+    /// 按当前配置生成修复选项，规则文件读取失败时一并返回错误信息
+    pub fn fix_options(&self) -> (FixOptions, Vec<String>) {
+        let profile = self.profile();
+        let (rules, errors) = load_rules(&profile.rule_files);
+        let options = FixOptions {
+            output_mode: profile.output.mode,
+            output_template: if profile.output.template.contains("{stem}") {
+                profile.output.template.clone()
+            } else {
+                DEFAULT_OUTPUT_TEMPLATE.to_string()
+            },
+            rules,
+            journal: logging::journal_path(),
+        };
+        (options, errors)
+    }
+}
+
+/// 设置文件位置，如 ~/.config/sc2replay-autofix/settings.toml 或 %APPDATA%\sc2replay-autofix\settings.toml
+pub fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("sc2replay-autofix").join("settings.toml"))
+}
+
+/// 用户版本表位置，与设置文件在同一目录
+pub fn builds_path() -> Option<PathBuf> {
+    settings_path().map(|p| p.with_file_name("builds.toml"))
+}
+
+/// 把用户版本表合并到内置版本表，文件不存在时跳过
+pub fn load_user_builds() -> anyhow::Result<()> {
+    if let Some(path) = builds_path().filter(|p| p.exists()) {
+        builds::load_build_file(&path)?;
+    }
+    Ok(())
+}
+
+/// 读取设置文件，文件不存在时返回默认设置
+pub fn load_settings() -> anyhow::Result<Settings> {
+    match settings_path() {
+        Some(path) if path.exists() => load_settings_from(&path),
+        _ => Ok(Settings::default()),
+    }
+}
+
+pub fn load_settings_from(path: &Path) -> anyhow::Result<Settings> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("无法读取设置文件: {}", path.display()))?;
+    parse_settings(&content).with_context(|| format!("设置文件格式错误: {}", path.display()))
+}
+
+pub fn parse_settings(content: &str) -> anyhow::Result<Settings> {
+    let mut value: toml::Value = toml::from_str(content)?;
+    // 没有版本号时按第一版处理，缺少的字段使用默认值
+    let version = value
+        .get("version")
+        .and_then(|v| v.as_integer())
+        .unwrap_or(1) as u32;
+
+    if version > SETTINGS_VERSION {
+        return Err(anyhow::anyhow!(
+            "设置文件版本{}高于当前程序支持的版本{}",
+            version,
+            SETTINGS_VERSION
+        ));
+    }
+
+    migrate(&mut value, version)?;
+    Ok(value.try_into()?)
+}
+
+// 逐个版本迁移设置文件
+fn migrate(value: &mut toml::Value, from_version: u32) -> anyhow::Result<()> {
+    let table = value.as_table_mut().context("设置文件顶层必须是表")?;
+
+    for version in from_version..SETTINGS_VERSION {
+        // v1：目录、账户、输出方式和规则文件移入默认配置
+        if version == 1 {
+            let mut profile = toml::Table::new();
+            for key in ["base_dir", "extra_replay_dirs", "categories", "accounts", "output", "rule_files"] {
+                if let Some(value) = table.remove(key) {
+                    profile.insert(key.to_string(), value);
+                }
+            }
+            let mut profiles = toml::Table::new();
+            profiles.insert(DEFAULT_PROFILE.to_string(), toml::Value::Table(profile));
+            table.insert("profiles".to_string(), toml::Value::Table(profiles));
+            table.insert("active_profile".to_string(), toml::Value::String(DEFAULT_PROFILE.to_string()));
+        }
+    }
+
+    table.insert("version".to_string(), toml::Value::Integer(SETTINGS_VERSION as i64));
+    Ok(())
+}
+
+/// 保存设置文件，返回保存的路径
+pub fn save_settings(settings: &Settings) -> anyhow::Result<PathBuf> {
+    let path = settings_path().context("无法确定配置目录")?;
+    save_settings_to(&path, settings)?;
+    Ok(path)
+}
+
+pub fn save_settings_to(path: &Path, settings: &Settings) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("无法创建配置目录: {}", parent.display()))?;
+    }
+
+    // 先写临时文件再替换，避免写入中断导致设置丢失
+    let content = toml::to_string_pretty(settings)?;
+    let tmp_path = path.with_extension("toml.tmp");
+    std::fs::write(&tmp_path, content)
+        .with_context(|| format!("无法写入设置文件: {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("无法保存设置文件: {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let mut settings = Settings::default();
+        settings.profile_mut().base_dir = Some(PathBuf::from("/tmp/StarCraft II"));
+        settings.profile_mut().accounts.push(AccountSetting {
+            dir: PathBuf::from("/tmp/StarCraft II/Accounts/1/5-S2-1-1"),
+            alias: "Alice".to_string(),
+            categories: Some(vec!["Multiplayer".to_string()]),
+            ..AccountSetting::default()
+        });
+        settings.select_profile("caster");
+        settings.profile_mut().output.mode = OutputMode::InPlace;
+
+        let content = toml::to_string_pretty(&settings).unwrap();
+        assert_eq!(parse_settings(&content).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_empty_base_dir_not_saved() {
+        let mut state = AppState::default();
+        assert_eq!(Settings::from_state(&state).profile().base_dir, None);
+
+        // 已保存的空路径不覆盖当前目录
+        state.replay_dir = PathBuf::from("/tmp/StarCraft II");
+        let mut settings = Settings::default();
+        settings.profile_mut().base_dir = Some(PathBuf::new());
+        settings.apply_to(&mut state);
+        assert_eq!(state.replay_dir, PathBuf::from("/tmp/StarCraft II"));
+        assert_eq!(Settings::from_state(&state).profile().base_dir, Some(PathBuf::from("/tmp/StarCraft II")));
+    }
+
+    #[test]
+    fn test_migrate_v1_settings() {
+        let content = r#"
+            version = 1
+            rule_files = ["rules.toml"]
+
+            [output]
+            mode = "in_place"
+
+            [monitor]
+            start_on_launch = true
+        "#;
+        let settings = parse_settings(content).unwrap();
+
+        assert_eq!(settings.profile_names(), vec![DEFAULT_PROFILE.to_string()]);
+        assert_eq!(settings.profile().rule_files, vec![PathBuf::from("rules.toml")]);
+        assert_eq!(settings.profile().output.mode, OutputMode::InPlace);
+        assert!(settings.monitor.start_on_launch);
+    }
+
+    #[test]
+    fn test_select_and_remove_profile() {
+        let mut settings = Settings::default();
+        assert!(settings.select_profile("caster"));
+        assert!(!settings.select_profile("caster"));
+        assert!(settings.remove_profile("caster"));
+        assert_eq!(settings.active_profile, DEFAULT_PROFILE);
+        assert!(!settings.remove_profile(DEFAULT_PROFILE));
+    }
+
+    #[test]
+    fn test_reject_newer_settings() {
+        let content = format!("version = {}\n", SETTINGS_VERSION + 1);
+        assert!(parse_settings(&content).is_err());
+    }
+}
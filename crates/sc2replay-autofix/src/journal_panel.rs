@@ -0,0 +1,95 @@
+use std::path::Path;
+use eframe::egui;
+use sc2replay_app::logging;
+use sc2replay_app::message::AppState;
+use sc2replay_core::journal::{self, JournalAction, JournalEntry};
+use sc2replay_core::log::LogSource;
+
+// 界面上最多显示的记录数，从最新的开始
+const MAX_SHOWN: usize = 200;
+
+// 修复记录：展开时读取记录文件，可按文字筛选；输入已有的录像路径时按路径和内容查找与其有关的记录
+#[derive(Default)]
+pub struct JournalPanel {
+    search: String,
+    entries: Vec<JournalEntry>,
+    loaded: bool,
+}
+
+impl JournalPanel {
+    fn reload(&mut self, state: &AppState) {
+        self.loaded = true;
+        let Some(path) = logging::journal_path() else {
+            return;
+        };
+        match journal::read_journal(&path) {
+            Ok(entries) => self.entries = entries,
+            Err(e) => state.bus.error(LogSource::App, format!("{:#}", e)),
+        }
+    }
+
+    // 符合筛选条件的记录，从新到旧
+    fn shown(&self) -> Vec<&JournalEntry> {
+        let search = self.search.trim();
+        let mut shown = if search.is_empty() {
+            self.entries.iter().collect()
+        } else if Path::new(search).is_file() {
+            journal::entries_for_file(&self.entries, Path::new(search))
+        } else {
+            let search = search.to_lowercase();
+            self.entries
+                .iter()
+                .filter(|e| {
+                    e.to_string().to_lowercase().contains(&search)
+                        || e.input_hash.as_deref().is_some_and(|h| h.starts_with(&search))
+                        || e.output_hash.as_deref().is_some_and(|h| h.starts_with(&search))
+                })
+                .collect::<Vec<_>>()
+        };
+        shown.reverse();
+        shown.truncate(MAX_SHOWN);
+        shown
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &AppState) {
+        egui::CollapsingHeader::new("修复记录")
+            .default_open(false)
+            .show(ui, |ui| {
+                if !self.loaded {
+                    self.reload(state);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("查找:");
+                    ui.text_edit_singleline(&mut self.search)
+                        .on_hover_text("文件名、规则或SHA-256；输入录像的完整路径时按内容查找，改名后也能找到");
+                    if ui.button("刷新").clicked() {
+                        self.reload(state);
+                    }
+                });
+
+                let shown = self.shown();
+                ui.label(format!("共{}条记录，显示{}条", self.entries.len(), shown.len()));
+                egui::ScrollArea::vertical()
+                    .id_source("journal")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for entry in shown {
+                            let text = egui::RichText::new(entry.to_string());
+                            let text = match entry.action {
+                                JournalAction::Fixed => text.color(egui::Color32::from_rgb(0, 160, 0)),
+                                JournalAction::Failed => text.color(egui::Color32::RED),
+                                JournalAction::NoMatch => text.color(egui::Color32::from_rgb(255, 140, 0)),
+                                JournalAction::Skipped | JournalAction::Reverted => text,
+                            };
+                            let hashes = format!(
+                                "原文件SHA-256: {}\n输出SHA-256: {}",
+                                entry.input_hash.as_deref().unwrap_or("-"),
+                                entry.output_hash.as_deref().unwrap_or("-"),
+                            );
+                            ui.label(text).on_hover_text(hashes);
+                        }
+                    });
+            });
+    }
+}
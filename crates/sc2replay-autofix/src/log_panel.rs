@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use eframe::egui;
+use rfd::FileDialog;
+use sc2replay_app::logging;
+use sc2replay_app::message::AppState;
+use sc2replay_core::log::{now_ms, outcome, LogLevel, LogRecord, LogSource};
+
+// 日志区：按级别、来源和文字筛选，可选中复制、打开相关文件、导出本次运行的日志
+pub struct LogPanel {
+    level: LogLevel,                // 显示的最低级别
+    hidden_sources: Vec<LogSource>, // 不显示的来源
+    search: String,
+    autoscroll: bool,
+    selected: HashSet<u64>,         // 选中日志的序号，见AppState::log_dropped
+    log_file: Option<PathBuf>,      // 本次运行写入的日志文件，未写文件时导出内存中的日志
+    session_start_ms: u64,
+}
+
+impl LogPanel {
+    pub fn new(log_file: Option<PathBuf>) -> Self {
+        Self {
+            level: LogLevel::Info,
+            hidden_sources: Vec::new(),
+            search: String::new(),
+            autoscroll: true,
+            selected: HashSet::new(),
+            log_file,
+            session_start_ms: now_ms(),
+        }
+    }
+
+    fn matches(&self, record: &LogRecord) -> bool {
+        let search = self.search.trim().to_lowercase();
+        record.level >= self.level
+            && !self.hidden_sources.contains(&record.source)
+            && (search.is_empty() || record.text().to_lowercase().contains(&search))
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &AppState) {
+        ui.horizontal(|ui| {
+            ui.label("操作日志:");
+            egui::ComboBox::from_id_source("log_level")
+                .selected_text(format!("{}及以上", self.level))
+                .show_ui(ui, |ui| {
+                    for level in LogLevel::ALL {
+                        ui.selectable_value(&mut self.level, level, level.label());
+                    }
+                });
+            for source in LogSource::ALL {
+                let mut shown = !self.hidden_sources.contains(&source);
+                if ui.checkbox(&mut shown, source.label()).changed() {
+                    if shown {
+                        self.hidden_sources.retain(|s| *s != source);
+                    } else {
+                        self.hidden_sources.push(source);
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("搜索:");
+            ui.text_edit_singleline(&mut self.search);
+            ui.checkbox(&mut self.autoscroll, "自动滚动");
+
+            let selected: Vec<String> = state
+                .log
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| self.selected.contains(&(state.log_dropped + *i as u64)))
+                .map(|(_, r)| r.to_string())
+                .collect();
+            if ui.add_enabled(!selected.is_empty(), egui::Button::new("复制所选")).clicked() {
+                ui.output_mut(|o| o.copied_text = selected.join("\n"));
+            }
+            if ui.add_enabled(!self.selected.is_empty(), egui::Button::new("取消选择")).clicked() {
+                self.selected.clear();
+            }
+            if ui.button("导出日志").clicked() {
+                self.export(state);
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .stick_to_bottom(self.autoscroll)
+            .show(ui, |ui| {
+                for (i, record) in state.log.iter().enumerate() {
+                    if !self.matches(record) {
+                        continue;
+                    }
+                    let id = state.log_dropped + i as u64;
+
+                    ui.horizontal(|ui| {
+                        // 按级别着色，修复成功的录像显示为绿色
+                        let line = format!("{} [{}] {}", record.time_string(), record.source, record.text());
+                        let color = match record.level {
+                            LogLevel::Error => Some(egui::Color32::RED),
+                            LogLevel::Warn => Some(egui::Color32::from_rgb(255, 140, 0)),
+                            LogLevel::Debug => Some(egui::Color32::GRAY),
+                            LogLevel::Info if record.outcome.as_deref() == Some(outcome::FIXED) => {
+                                Some(egui::Color32::from_rgb(0, 160, 0))
+                            }
+                            LogLevel::Info => None,
+                        };
+                        let text = egui::RichText::new(line);
+                        let text = match color {
+                            Some(color) => text.color(color),
+                            None => text,
+                        };
+
+                        // 点击选中或取消选中
+                        let selected = self.selected.contains(&id);
+                        if ui.selectable_label(selected, text).clicked() {
+                            if selected {
+                                self.selected.remove(&id);
+                            } else {
+                                self.selected.insert(id);
+                            }
+                        }
+                        if let Some(path) = &record.path
+                            && ui.small_button("打开").on_hover_text("在文件管理器中显示").clicked()
+                            && let Err(e) = reveal_in_file_manager(path)
+                        {
+                            state.bus.error(LogSource::App, format!("无法打开文件位置: {:#}", e));
+                        }
+                    });
+                }
+            });
+    }
+
+    // 选择导出位置后在后台导出本次运行的完整日志
+    fn export(&self, state: &AppState) {
+        let bus = state.bus.clone();
+        let log_file = self.log_file.clone();
+        let in_memory: Vec<LogRecord> = state.log.iter().cloned().collect();
+        let since = self.session_start_ms;
+
+        std::thread::spawn(move || {
+            let Some(path) = FileDialog::new()
+                .set_file_name("sc2replay-log.txt")
+                .add_filter("文本", &["txt"])
+                .add_filter("JSON", &["json"])
+                .save_file()
+            else {
+                return;
+            };
+
+            // 日志文件中包括已经从日志区移除的记录
+            let records = match log_file.as_deref().and_then(Path::parent) {
+                Some(dir) => logging::read_records(dir, "gui", since),
+                None => Ok(in_memory),
+            };
+            match records.and_then(|records| logging::export_records(&records, &path).map(|_| records.len())) {
+                Ok(count) => bus.log(
+                    LogRecord::new(LogLevel::Info, LogSource::App, format!("已导出{}条日志", count)).with_path(&path),
+                ),
+                Err(e) => bus.error(LogSource::App, format!("导出日志失败: {:#}", e)),
+            }
+        });
+    }
+}
+
+// 在系统文件管理器中显示文件，目录则直接打开
+fn reveal_in_file_manager(path: &Path) -> anyhow::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = std::process::Command::new("explorer");
+        if path.is_dir() {
+            command.arg(path);
+        } else {
+            command.arg(format!("/select,{}", path.display()));
+        }
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("open");
+        if !path.is_dir() {
+            command.arg("-R");
+        }
+        command.arg(path);
+        command
+    } else {
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(if path.is_dir() { path } else { path.parent().unwrap_or(path) });
+        command
+    };
+    command.spawn()?;
+    Ok(())
+}
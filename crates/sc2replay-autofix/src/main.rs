@@ -0,0 +1,676 @@
+use eframe::egui;
+use rfd::FileDialog;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use sc2replay_app::message::AppState;
+use sc2replay_app::settings::{self, Settings};
+use sc2replay_app::autostart::{self, AutostartStatus};
+use sc2replay_app::{cli, daemon, instance, logging};
+use sc2replay_core::builds;
+use sc2replay_core::discovery::{self, ReplayCategory, ReplayLocation};
+use sc2replay_core::events::{DirectoryChange, Event, EventBus};
+use sc2replay_core::fixer::OutputMode;
+use sc2replay_core::log::LogSource;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+mod journal_panel;
+mod log_panel;
+use journal_panel::JournalPanel;
+use log_panel::LogPanel;
+
+// 后台线程发给界面的命令
+enum UiCommand {
+    AddRuleFile(PathBuf),
+    ShowWindow, // 再次启动程序时由新实例发来
+}
+
+struct SC2ReplayFixerApp {
+    state: AppState,
+    new_category_folder: String, // 待添加的自定义录像类型目录名
+    base_dir_edit: String,       // 基础目录输入框内容
+    base_dir_shown: PathBuf,     // 输入框内容对应的基础目录，目录变化时刷新输入框
+    new_profile_name: String,    // 待新建的配置名
+    last_daemon_poll: Instant,   // 上次同步守护进程状态的时间
+    on_top_pinned: bool,         // 为切换到前台临时置顶，下一帧取消
+    start_minimized: bool,       // 命令行--minimized，第一帧时最小化窗口
+    autostart_status: AutostartStatus,
+    events: Receiver<Event>,
+    commands: (Sender<UiCommand>, Receiver<UiCommand>),
+    log_panel: LogPanel,
+    journal_panel: JournalPanel,
+}
+
+impl SC2ReplayFixerApp {
+    // profile为命令行--profile指定的配置名
+    fn new(
+        profile: Option<String>,
+        minimized: bool,
+        bus: EventBus,
+        commands: (Sender<UiCommand>, Receiver<UiCommand>),
+    ) -> Self {
+        // 读取设置文件，失败时使用默认设置
+        let (mut settings, settings_error) = match settings::load_settings() {
+            Ok(settings) => (settings, None),
+            Err(e) => (Settings::default(), Some(e)),
+        };
+        let profile_created = profile.map(|name| settings.select_profile(&name)).unwrap_or(false);
+
+        let base_dirs = discovery::find_sc2_base_dirs();
+        let base_dir = discovery::find_sc2_base_dir()
+            .unwrap_or_else(|| PathBuf::from(".").join("StarCraft II"));
+
+        // 检查当前是否已设置开机自启动
+        let (autostart_status, autostart_error) = match autostart::auto_start_status(&settings.autostart) {
+            Ok(status) => (status, None),
+            Err(e) => (AutostartStatus::Disabled, Some(e)),
+        };
+
+        // 在写第一条日志之前订阅，不漏掉启动过程中的事件
+        let events = bus.subscribe();
+        let log_file = logging::spawn_file_logger(&bus, "gui", &settings.log).unwrap_or_else(|e| {
+            bus.error(LogSource::App, format!("{:#}", e));
+            None
+        });
+        bus.info(LogSource::App, "🚀 SC2Replay修复工具已启动");
+        if let Err(e) = settings::load_user_builds() {
+            bus.error(LogSource::App, format!("{:#}", e));
+        }
+
+        let mut state = AppState {
+            replay_dir: base_dir,
+            auto_fix: true,
+            auto_start: autostart_status.is_enabled(),
+            bus,
+            ..AppState::default()
+        };
+        let bus = state.bus.clone();
+        if let Some(e) = settings_error {
+            bus.error(LogSource::App, format!("读取设置文件失败，已使用默认设置: {:#}", e));
+        }
+        if profile_created {
+            bus.info(LogSource::App, format!("已新建配置: {}", settings.active_profile));
+        }
+        bus.info(LogSource::App, format!("当前配置: {}", settings.active_profile));
+        if let Some(e) = autostart_error {
+            bus.error(LogSource::App, format!("无法读取开机自启动项: {:#}", e));
+        }
+        if autostart_status.needs_repair() {
+            bus.warn(LogSource::App, format!("开机自启动项不会启动当前程序({})，可点击“修复自启动”", autostart_status));
+        }
+
+        let start_monitor = settings.monitor.start_on_launch;
+        state.apply_settings(settings);
+
+        for base in &base_dirs {
+            bus.info(LogSource::Discovery, format!("🔍 发现SC2文档目录({}): {}", base.source, base.path.display()));
+        }
+        bus.info(LogSource::Discovery, format!("📂 找到{}个录像目录", state.all_replay_dirs.len()));
+
+        // 后台守护进程已在运行时连接它，不再启动本地监控
+        if let Some(client) = daemon::DaemonClient::connect() {
+            bus.info(LogSource::Monitor, format!("已连接到后台守护进程: {}", daemon::endpoint_path().display()));
+            state.daemon = Some(client);
+            state.poll_daemon();
+        } else if start_monitor && state.auto_fix {
+            state.start_monitor();
+        }
+
+        Self {
+            base_dir_edit: state.replay_dir.to_str().unwrap_or("").to_string(),
+            base_dir_shown: state.replay_dir.clone(),
+            state,
+            new_category_folder: String::new(),
+            new_profile_name: String::new(),
+            last_daemon_poll: Instant::now(),
+            on_top_pinned: false,
+            start_minimized: minimized,
+            autostart_status,
+            events,
+            commands,
+            log_panel: LogPanel::new(log_file),
+            journal_panel: JournalPanel::default(),
+        }
+    }
+}
+
+impl eframe::App for SC2ReplayFixerApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.start_minimized {
+            frame.set_minimized(true);
+            self.start_minimized = false;
+        }
+        if self.on_top_pinned {
+            frame.set_always_on_top(false);
+            self.on_top_pinned = false;
+        }
+
+        // 处理事件和后台线程的命令
+        while let Ok(event) = self.events.try_recv() {
+            self.state.handle_event(event);
+        }
+        while let Ok(command) = self.commands.1.try_recv() {
+            match command {
+                UiCommand::AddRuleFile(path) => self.state.add_rule_file(path),
+                UiCommand::ShowWindow => {
+                    // 再次启动程序时把已有窗口切换到前台
+                    frame.set_visible(true);
+                    frame.set_minimized(false);
+                    frame.set_always_on_top(true);
+                    self.on_top_pinned = true;
+                }
+            }
+        }
+        if self.state.daemon.is_some() && self.last_daemon_poll.elapsed() >= Duration::from_secs(1) {
+            self.last_daemon_poll = Instant::now();
+            self.state.poll_daemon();
+        }
+        if self.base_dir_shown != self.state.replay_dir {
+            self.base_dir_shown = self.state.replay_dir.clone();
+            self.base_dir_edit = self.state.replay_dir.to_str().unwrap_or("").to_string();
+        }
+
+        // 强制UI刷新
+        ctx.request_repaint_after(Duration::from_millis(5));
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // 设置背景色防止黑屏
+            ui.visuals_mut().panel_fill = egui::Color32::from_rgb(248, 248, 248);
+
+            // 标题
+            ui.heading("SC2Replay自动修复工具");
+            ui.separator();
+
+            // 配置选择
+            ui.horizontal(|ui| {
+                ui.label("当前配置:");
+                let mut selected = self.state.settings.active_profile.clone();
+                egui::ComboBox::from_id_source("profile")
+                    .selected_text(&selected)
+                    .show_ui(ui, |ui| {
+                        for name in self.state.settings.profile_names() {
+                            ui.selectable_value(&mut selected, name.clone(), name);
+                        }
+                    });
+                if selected != self.state.settings.active_profile {
+                    self.state.switch_profile(&selected);
+                }
+
+                ui.text_edit_singleline(&mut self.new_profile_name);
+                let name = self.new_profile_name.trim().to_string();
+                if ui.button("新建配置").clicked() && !name.is_empty() {
+                    self.state.switch_profile(&name);
+                    self.new_profile_name.clear();
+                }
+                if ui.button("删除配置").clicked() {
+                    self.state.remove_active_profile();
+                }
+            });
+
+            // 基础目录选择
+            ui.horizontal(|ui| {
+                ui.label("SC2基础目录:");
+                let response = ui.text_edit_singleline(&mut self.base_dir_edit);
+
+                // 输入路径后按回车应用
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    let dir = PathBuf::from(self.base_dir_edit.trim());
+                    self.state.set_base_dir(dir);
+                }
+
+                if ui.button("选择目录").clicked() {
+                    let bus = self.state.bus.clone();
+                    std::thread::spawn(move || {
+                        if let Some(dir) = FileDialog::new().pick_folder() {
+                            // 由事件处理负责重新扫描并更新目录列表
+                            bus.publish(Event::DirectoriesChanged(DirectoryChange::BaseDir(dir)));
+                        }
+                    });
+                }
+            });
+
+            // 显示找到的录像目录
+            ui.collapsing(
+                format!("已找到{}个录像目录", self.state.all_replay_dirs.len()),
+                |ui| {
+                    for dir in &self.state.all_replay_dirs {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("移除").clicked() {
+                                self.state.bus.publish(Event::DirectoriesChanged(DirectoryChange::Removed(dir.clone())));
+                            }
+                            ui.label(self.state.dir_label(dir))
+                                .on_hover_text(dir.to_str().unwrap_or(""));
+                        });
+                    }
+
+                    if ui.button("添加目录").clicked() {
+                        let bus = self.state.bus.clone();
+                        std::thread::spawn(move || {
+                            if let Some(dir) = FileDialog::new().pick_folder() {
+                                bus.publish(Event::DirectoriesChanged(DirectoryChange::Added(dir)));
+                            }
+                        });
+                    }
+                },
+            );
+
+            // 录像类型设置
+            ui.collapsing("录像类型", |ui| {
+                let mut changed = false;
+
+                ui.label("默认启用的类型:");
+                ui.horizontal_wrapped(|ui| {
+                    for category in &mut self.state.replay_categories.categories {
+                        let text = format!("{} ({})", category.label, category.folder);
+                        changed |= ui.checkbox(&mut category.enabled, text).changed();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("其他类型目录:");
+                    ui.text_edit_singleline(&mut self.new_category_folder);
+                    let folder = self.new_category_folder.trim().to_string();
+                    if ui.button("添加类型").clicked() && !folder.is_empty() {
+                        let categories = &mut self.state.replay_categories.categories;
+                        if !categories.iter().any(|c| c.folder == folder) {
+                            categories.push(ReplayCategory::new(&folder, &folder, true));
+                            changed = true;
+                        }
+                        self.new_category_folder.clear();
+                    }
+                });
+
+
+                if changed {
+                    self.state.rescan_replay_dirs();
+                    self.state.bus.info(LogSource::Discovery, format!("📂 录像类型已更新，共{}个录像目录", self.state.all_replay_dirs.len()));
+                }
+            });
+
+            // 账户设置：别名、修复/监控开关以及各账户的录像类型
+            ui.collapsing("账户设置", |ui| {
+                let mut accounts: Vec<&ReplayLocation> = Vec::new();
+                for location in &self.state.replay_locations {
+                    if !accounts.iter().any(|l| l.account_dir == location.account_dir) {
+                        accounts.push(location);
+                    }
+                }
+
+                let mut option_changes = Vec::new();
+                let mut toggles = Vec::new();
+                for account in accounts {
+                    let account_dir = &account.account_dir;
+                    let mut options = self.state.account_options(account_dir);
+
+                    ui.group(|ui| {
+                        let mut title = self.state.account_name(account);
+                        if let Some(region) = account.region {
+                            title.push_str(&format!(" ({})", region));
+                        }
+                        if let Some(handle) = &account.toon_handle {
+                            title.push_str(&format!(" · {}", handle));
+                        }
+                        ui.label(egui::RichText::new(title).strong())
+                            .on_hover_text(account_dir.to_str().unwrap_or(""));
+
+                        ui.horizontal(|ui| {
+                            ui.label("别名:");
+                            let mut changed = ui.text_edit_singleline(&mut options.alias).changed();
+                            changed |= ui.checkbox(&mut options.fix, "批量修复").changed();
+                            changed |= ui.checkbox(&mut options.monitor, "监控").changed();
+                            if changed {
+                                option_changes.push((account_dir.clone(), options.clone()));
+                            }
+                        });
+
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("录像类型:");
+                            for location in self.state.replay_locations.iter().filter(|l| &l.account_dir == account_dir) {
+                                let mut enabled = self
+                                    .state
+                                    .replay_categories
+                                    .is_enabled(account_dir, &location.category);
+                                if ui.checkbox(&mut enabled, &location.category).changed() {
+                                    toggles.push((account_dir.clone(), location.category.clone(), enabled));
+                                }
+                            }
+                        });
+                    });
+                }
+
+                for (account_dir, options) in option_changes {
+                    self.state.set_account_options(&account_dir, options);
+                }
+
+                for (account_dir, folder, enabled) in &toggles {
+                    self.state.replay_categories.set_enabled(account_dir, folder, *enabled);
+                }
+                if !toggles.is_empty() {
+                    self.state.rescan_replay_dirs();
+                    self.state.bus.info(LogSource::Discovery, format!("📂 录像类型已更新，共{}个录像目录", self.state.all_replay_dirs.len()));
+                }
+            });
+
+            ui.add_space(10.0);
+
+            // 功能开关
+            let mut auto_fix = self.state.auto_fix;
+            // 如果监控正在运行，则禁用复选框
+            let checkbox_response = if self.state.watcher_running {
+                ui.scope(|ui| {
+                    ui.set_enabled(false);
+                    ui.checkbox(&mut auto_fix, "自动修复新录像")
+                }).inner
+            } else {
+                ui.checkbox(&mut auto_fix, "自动修复新录像")
+            };
+            
+            if checkbox_response.changed() {
+                self.state.auto_fix = auto_fix;
+            }
+
+            ui.horizontal(|ui| {
+                let mut auto_start = self.state.auto_start;
+                if ui.checkbox(&mut auto_start, "开机自动启动").changed() {
+                    self.state.auto_start = auto_start;
+                }
+
+                // 程序被移动后自启动项失效，提示并提供修复
+                if self.autostart_status.needs_repair() {
+                    ui.colored_label(egui::Color32::from_rgb(220, 120, 0), self.autostart_status.to_string());
+                    if ui.button("修复自启动").clicked() {
+                        match autostart::repair_auto_start(&self.state.settings.autostart) {
+                            Ok(launch) => self.state.bus.info(LogSource::App, format!("✅ 自启动项已改为: {}", launch.exe.display())),
+                            Err(e) => self.state.bus.error(LogSource::App, format!("修复自启动失败: {:#}", e)),
+                        }
+                        self.autostart_status = autostart::auto_start_status(&self.state.settings.autostart)
+                            .unwrap_or(AutostartStatus::Disabled);
+                    }
+                }
+            });
+            if self.state.auto_start {
+                // 启动方式，点击保存设置后生效
+                let options = &mut self.state.settings.autostart;
+                let active_profile = self.state.settings.active_profile.clone();
+                ui.indent("autostart_options", |ui| {
+                    ui.checkbox(&mut options.minimized, "启动后最小化窗口");
+                    ui.checkbox(&mut options.headless, "不打开窗口，在后台运行守护进程");
+                    let mut use_profile = options.profile.is_some();
+                    if ui.checkbox(&mut use_profile, format!("启动时使用配置: {}", active_profile)).changed() {
+                        options.profile = use_profile.then_some(active_profile);
+                    }
+                });
+            }
+
+            // 修复与监控相关设置，点击保存设置后生效
+            ui.collapsing("修复设置", |ui| {
+                let settings = &mut self.state.settings;
+                let profile = settings.profile_mut();
+
+                ui.horizontal(|ui| {
+                    ui.label("输出方式:");
+                    ui.radio_value(&mut profile.output.mode, OutputMode::Suffix, "生成新文件");
+                    ui.radio_value(&mut profile.output.mode, OutputMode::InPlace, "覆盖原文件(保留.bak备份)");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("文件名模板:");
+                    ui.text_edit_singleline(&mut profile.output.template)
+                        .on_hover_text("{stem}为原文件名，如{stem}-FIXED");
+                });
+
+                ui.label("规则文件:");
+                let mut removed = None;
+                for (i, path) in profile.rule_files.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("移除").clicked() {
+                            removed = Some(i);
+                        }
+                        ui.label(path.to_str().unwrap_or(""));
+                    });
+                }
+                if let Some(i) = removed {
+                    profile.rule_files.remove(i);
+                }
+                if ui.button("添加规则文件").clicked() {
+                    let sender = self.commands.0.clone();
+                    std::thread::spawn(move || {
+                        if let Some(path) = FileDialog::new().add_filter("TOML", &["toml"]).pick_file() {
+                            let _ = sender.send(UiCommand::AddRuleFile(path));
+                        }
+                    });
+                }
+
+                ui.checkbox(&mut settings.monitor.start_on_launch, "启动后自动开始监控");
+                ui.checkbox(&mut settings.monitor.watch_accounts, "自动发现新账户目录");
+                ui.horizontal(|ui| {
+                    ui.label("静默时间(毫秒):");
+                    ui.add(egui::DragValue::new(&mut settings.monitor.quiet_period_ms).clamp_range(200..=60000));
+                });
+                ui.checkbox(&mut settings.ui.show_monitor_panel, "显示监控状态");
+                ui.horizontal(|ui| {
+                    ui.label("日志最大行数:");
+                    ui.add(egui::DragValue::new(&mut settings.ui.max_log_lines).clamp_range(50..=5000));
+                });
+            });
+
+            if ui.button("保存设置").clicked() {
+                let settings = Settings::from_state(&self.state);
+                self.state.settings = settings.clone();
+
+                // 立即应用修复选项和监控静默时间
+                self.state.load_fix_options(&settings);
+                if let Some(instance) = &self.state.monitor_instance {
+                    instance.set_quiet_period(Duration::from_millis(settings.monitor.quiet_period_ms));
+                }
+
+                if let Err(e) = autostart::set_auto_start(self.state.auto_start, &settings.autostart) {
+                    self.state.bus.error(LogSource::App, format!("开机自启动设置失败: {:#}", e));
+                }
+                self.autostart_status = autostart::auto_start_status(&settings.autostart)
+                    .unwrap_or(AutostartStatus::Disabled);
+
+                let daemon = self.state.daemon.clone();
+                let bus = self.state.bus.clone();
+                std::thread::spawn(move || {
+                    match settings::save_settings(&settings) {
+                        Ok(path) => bus.info(LogSource::App, format!("✅ 设置已保存: {}", path.display())),
+                        Err(e) => bus.error(LogSource::App, format!("设置保存失败: {:#}", e)),
+                    }
+                    // 守护进程按保存后的设置重新加载当前配置
+                    if let Some(daemon) = daemon {
+                        let params = serde_json::json!({ "profile": settings.active_profile });
+                        if let Err(e) = daemon.call("reload", params) {
+                            bus.error(LogSource::Monitor, format!("守护进程重新加载设置失败: {}", e));
+                        }
+                    }
+                });
+            }
+
+            ui.add_space(10.0);
+
+            // 操作按钮
+            ui.horizontal(|ui| {
+                // 批量修复所有目录
+                if ui.button("批量修复所有录像").clicked() {
+                    let dirs = self.state.fix_dirs();
+                    self.state.run_batch(dirs);
+                }
+
+                // 监控开关
+                if self.state.watcher_running {
+                    if ui
+                        .button(
+                            egui::RichText::new("停止监控")
+                                .color(egui::Color32::from_rgb(220, 0, 0)),
+                        )
+                        .clicked()
+                    {
+                        // 立即停止监控
+                        self.state.stop_monitor();
+                    }
+
+                    // 暂停/恢复修复，暂停期间新录像会排队等待
+                    let pause_text = if self.state.watcher_paused { "恢复监控" } else { "暂停监控" };
+                    if ui.button(pause_text).clicked() {
+                        self.state.set_watcher_paused(!self.state.watcher_paused);
+                    }
+                } else {
+                    // 只有当自动修复复选框被勾选时才启用启动监控按钮
+                    let start_btn = ui.scope(|ui| {
+                        ui.set_enabled(self.state.auto_fix);
+                        ui.button(
+                            egui::RichText::new("启动监控").color(egui::Color32::from_rgb(0, 160, 0)),
+                        )
+                    }).inner;
+                    
+                    if start_btn.clicked() {
+                        // 同步启动监控，确保正确保存实例
+                        self.state.start_monitor();
+                        ctx.request_repaint();
+                    }
+                }
+            });
+
+            // 批量修复进度
+            if let Some((done, total)) = self.state.batch_progress.filter(|(done, total)| done < total) {
+                ui.add(
+                    egui::ProgressBar::new(done as f32 / total as f32)
+                        .text(format!("批量修复 {}/{}", done, total)),
+                );
+            }
+
+            // 监控状态面板
+            let snapshot = match &self.state.monitor_instance {
+                Some(instance) => Some(instance.snapshot()),
+                None => self.state.daemon_snapshot.clone(),
+            };
+            if let Some(snapshot) = snapshot.filter(|_| self.state.settings.ui.show_monitor_panel) {
+                ui.add_space(10.0);
+                let title = if self.state.daemon.is_some() { "监控状态(守护进程)" } else { "监控状态" };
+                egui::CollapsingHeader::new(title)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for line in snapshot.to_string().lines() {
+                            ui.label(line);
+                        }
+                    });
+            }
+
+            ui.add_space(20.0);
+
+            // 日志区域
+            ui.group(|ui| self.log_panel.show(ui, &self.state));
+
+            ui.add_space(10.0);
+            self.journal_panel.show(ui, &self.state);
+
+            ui.add_space(10.0);
+            // 支持的版本取自当前规则限定的版本
+            let versions = builds::supported_versions(&self.state.fix_options.rules);
+            ui.label(format!("[提示] 本地处理，文件不上传 | 支持版本: {}", versions.join("、")));
+        });
+    }
+}
+
+// 各平台常见的中文字体，按顺序使用第一个存在的
+fn cjk_font_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if cfg!(windows) {
+        let fonts = PathBuf::from(std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into())).join("Fonts");
+        for name in ["msyh.ttc", "msyh.ttf", "simhei.ttf", "simsun.ttc"] {
+            candidates.push(fonts.join(name));
+        }
+    } else if cfg!(target_os = "macos") {
+        for path in [
+            "/System/Library/Fonts/PingFang.ttc",
+            "/System/Library/Fonts/STHeiti Medium.ttc",
+            "/System/Library/Fonts/Hiragino Sans GB.ttc",
+            "/Library/Fonts/Arial Unicode.ttf",
+        ] {
+            candidates.push(PathBuf::from(path));
+        }
+    } else {
+        for path in [
+            "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+            "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+            "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+            "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+            "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+            "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+        ] {
+            candidates.push(PathBuf::from(path));
+        }
+    }
+    candidates
+}
+
+// 运行时从系统字体目录加载中文字体，找不到时使用egui的默认字体（中文无法显示）
+fn load_global_font(ctx: &egui::Context) {
+    let Some(data) = cjk_font_candidates().into_iter().find_map(|path| std::fs::read(path).ok()) else {
+        eprintln!("[提示]未找到中文字体，使用默认字体");
+        return;
+    };
+
+    let mut fonts = eframe::egui::FontDefinitions::default();
+    fonts.font_data.insert("cjk".to_owned(), eframe::egui::FontData::from_owned(data));
+
+    // 比例字体和等宽字体都优先使用中文字体，其余字符回退到默认字体
+    for family in [eframe::egui::FontFamily::Proportional, eframe::egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().insert(0, "cjk".to_owned());
+    }
+
+    ctx.set_fonts(fonts);
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli_args = match cli::parse_args(&args) {
+        Ok(cli_args) => cli_args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::usage());
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    };
+
+    // 带命令时以命令行模式运行，不创建窗口
+    if cli_args.command.is_some() {
+        std::process::exit(cli::run(&cli_args));
+    }
+    let profile = cli_args.profile;
+    let minimized = cli_args.minimized;
+
+    // 只允许一个图形界面实例，再次启动时切换到已有窗口
+    if let Some(pid) = instance::notify_existing() {
+        eprintln!("程序已在运行(进程{})，已切换到已打开的窗口", pid);
+        return Ok(());
+    }
+    let commands = unbounded();
+    let show_sender = commands.0.clone();
+    // 同时启动的另一个实例先拿到了锁，不再打开窗口
+    if let Err(e) = instance::acquire(move || {
+        let _ = show_sender.send(UiCommand::ShowWindow);
+    }) {
+        eprintln!("[失败]无法创建单实例锁: {:#}", e);
+        std::process::exit(1);
+    }
+
+    let native_options = eframe::NativeOptions {
+        initial_window_size: Some(egui::vec2(900.0, 700.0)),
+        default_theme: eframe::Theme::Light,
+        vsync: true,
+        renderer: eframe::Renderer::Glow,
+        ..eframe::NativeOptions::default()
+    };
+
+    eframe::run_native(
+        "SC2Replay修复工具",
+        native_options,
+        Box::new(move |_cc| {
+            load_global_font(&_cc.egui_ctx);
+            Box::new(SC2ReplayFixerApp::new(profile, minimized, EventBus::new(), commands))
+        }),
+    )
+    .map_err(|e| anyhow::anyhow!("GUI启动失败: {}", e))?;
+
+    instance::release();
+    Ok(())
+}
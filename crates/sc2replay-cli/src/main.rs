@@ -0,0 +1,14 @@
+use sc2replay_app::cli;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli_args = match cli::parse_args(&args) {
+        Ok(cli_args) => cli_args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::usage());
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    };
+
+    std::process::exit(cli::run(&cli_args));
+}
@@ -0,0 +1,72 @@
+//! 比较读入内存和流式两种修复方式的耗时
+//!
+//! 生成不同大小的录像，分别测量需要修复和无需修复（已修复过的录像）两种情况。
+//! 流式处理只读入录像头，其余部分由系统复制，内存占用与录像大小无关；
+//! 无需修复时流式处理只读开头，耗时不随录像大小增长。
+//! 另外测量写入修复记录的情况，此时每个录像都要完整读一遍计算SHA-256。
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use sc2replay_core::fixer::{fix_file_with, FixOptions, FixOutcome, ReadMode, SEARCH_BYTES, TARGET_BYTES};
+
+const SIZES_MB: &[usize] = &[1, 16, 64];
+const ITERATIONS: u32 = 5;
+
+// 只有用户数据头和录像头是有效的，其后为填充数据，字节替换规则不需要解析归档
+fn synthetic_replay(size: usize, header: &[u8]) -> Vec<u8> {
+    let archive_offset = 0x400usize;
+    let mut data = b"MPQ\x1B".to_vec();
+    data.extend_from_slice(&0x200u32.to_le_bytes());
+    data.extend_from_slice(&(archive_offset as u32).to_le_bytes());
+    data.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    data.extend_from_slice(header);
+    data.resize(archive_offset, 0);
+    data.extend((data.len()..size).map(|i| (i % 251) as u8));
+    data
+}
+
+fn measure(input: &Path, options: &FixOptions, mode: ReadMode, expect_fixed: bool) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let outcome = fix_file_with(input, options, mode).expect("修复失败");
+        total += start.elapsed();
+        assert_eq!(matches!(outcome, FixOutcome::Fixed { .. }), expect_fixed);
+    }
+    total / ITERATIONS
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join("sc2replay-autofix-bench");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).expect("无法创建临时目录");
+    let input = dir.join("bench.SC2Replay");
+    let journal = dir.join("journal.jsonl");
+
+    println!("{:>8} {:>10} {:>12} {:>12} {:>10}", "大小", "情况", "内存(ms)", "流式(ms)", "加速");
+    for &size_mb in SIZES_MB {
+        let cases = [
+            ("需要修复", SEARCH_BYTES, true, None),
+            ("无需修复", TARGET_BYTES, false, None),
+            ("修复+记录", SEARCH_BYTES, true, Some(journal.clone())),
+            ("无需+记录", TARGET_BYTES, false, Some(journal.clone())),
+        ];
+        for (case, header, fixed, journal) in cases {
+            let options = FixOptions { journal, ..FixOptions::default() };
+            fs::write(&input, synthetic_replay(size_mb << 20, header)).expect("无法写入录像");
+            let in_memory = measure(&input, &options, ReadMode::InMemory, fixed);
+            let streaming = measure(&input, &options, ReadMode::Streaming, fixed);
+            println!(
+                "{:>6}MB {:>10} {:>12.2} {:>12.2} {:>9.2}x",
+                size_mb,
+                case,
+                in_memory.as_secs_f64() * 1000.0,
+                streaming.as_secs_f64() * 1000.0,
+                in_memory.as_secs_f64() / streaming.as_secs_f64()
+            );
+        }
+    }
+
+    fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::RwLock;
+use anyhow::Context;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::fixer::TARGET_BASE_BUILD;
+use crate::rules::PatchRule;
+
+// 内置版本表，格式见文件开头的说明
+const BUILTIN_BUILDS: &str = include_str!("../data/builds.toml");
+
+/// 版本所在的服务器
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildRegion {
+    #[default]
+    Global,
+    Cn,
+}
+
+impl fmt::Display for BuildRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildRegion::Global => write!(f, "国际服"),
+            BuildRegion::Cn => write!(f, "国服"),
+        }
+    }
+}
+
+/// 一个base build在一个服务器上对应的版本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub base_build: u32,
+    /// 如"5.0.15.95687"
+    pub version: String,
+    /// 如"2024-03-26"，未知时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_date: Option<String>,
+    #[serde(default)]
+    pub region: BuildRegion,
+}
+
+impl BuildInfo {
+    /// 版本号等于version或以version开头，如"5.0.15"匹配"5.0.15.95687"
+    pub fn matches_version(&self, version: &str) -> bool {
+        self.version == version
+            || self.version.strip_prefix(version).is_some_and(|rest| rest.starts_with('.'))
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}（{}", self.version, self.region)?;
+        if let Some(date) = &self.release_date {
+            write!(f, "，{}发布", date)?;
+        }
+        write!(f, "）")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildFile {
+    #[serde(default, rename = "build")]
+    builds: Vec<BuildInfo>,
+}
+
+lazy_static! {
+    // 同一个base build在国际服和国服可以各有一条
+    static ref BUILDS: RwLock<BTreeMap<(u32, BuildRegion), BuildInfo>> = RwLock::new(
+        parse_builds(BUILTIN_BUILDS)
+            .expect("内置版本表格式错误")
+            .into_iter()
+            .map(|b| ((b.base_build, b.region), b))
+            .collect()
+    );
+}
+
+pub fn parse_builds(content: &str) -> anyhow::Result<Vec<BuildInfo>> {
+    let file: BuildFile = toml::from_str(content)?;
+    Ok(file.builds)
+}
+
+/// 读取用户的版本表并合并到内置表中，base_build和服务器都相同的条目以用户文件为准，返回读取的条目数
+pub fn load_build_file(path: &Path) -> anyhow::Result<usize> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("无法读取版本表: {}", path.display()))?;
+    let builds = parse_builds(&content).with_context(|| format!("版本表格式错误: {}", path.display()))?;
+
+    let count = builds.len();
+    if let Ok(mut table) = BUILDS.write() {
+        table.extend(builds.into_iter().map(|b| ((b.base_build, b.region), b)));
+    }
+    Ok(count)
+}
+
+/// 查找base build在各个服务器上对应的版本，先国际服后国服
+pub fn lookup(base_build: u32) -> Vec<BuildInfo> {
+    BUILDS
+        .read()
+        .map(|t| t.range((base_build, BuildRegion::Global)..=(base_build, BuildRegion::Cn)).map(|(_, b)| b.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// 版本表中的所有版本，按base build和服务器排序
+pub fn all_builds() -> Vec<BuildInfo> {
+    BUILDS.read().map(|t| t.values().cloned().collect()).unwrap_or_default()
+}
+
+/// base build的显示名称，有多个服务器时依次列出，版本表中没有时只显示编号
+pub fn build_label(base_build: u32) -> String {
+    let builds = lookup(base_build);
+    if builds.is_empty() {
+        return format!("base build {}", base_build);
+    }
+    builds.iter().map(|b| b.to_string()).collect::<Vec<_>>().join("、")
+}
+
+/// 规则限定的各个版本的显示名称，用于说明支持哪些版本
+///
+/// 按版本号限定的规则取版本表中所有匹配的版本；没有规则限定版本时为内置规则修正到的版本。
+pub fn supported_versions(rules: &[PatchRule]) -> Vec<String> {
+    let mut builds: Vec<u32> = rules.iter().flat_map(|r| r.base_builds.iter().copied()).collect();
+    for rule in rules.iter().filter(|r| !r.versions.is_empty()) {
+        builds.extend(all_builds().into_iter().map(|b| b.base_build).filter(|b| rule.applies_to(*b)));
+    }
+    if builds.is_empty() {
+        builds.push(TARGET_BASE_BUILD);
+    }
+    builds.sort_unstable();
+    builds.dedup();
+    builds.into_iter().map(build_label).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::parse_rules;
+
+    #[test]
+    fn test_builtin_builds() {
+        // 同一个base build在两个服务器上各有一条
+        let builds = lookup(TARGET_BASE_BUILD);
+        assert_eq!(builds.iter().map(|b| b.region).collect::<Vec<_>>(), vec![BuildRegion::Global, BuildRegion::Cn]);
+        assert!(builds.iter().all(|b| b.version == "5.0.15.95687"));
+        assert_eq!(builds[1].to_string(), "5.0.15.95687（国服）");
+        assert!(all_builds().windows(2).all(|w| (w[0].base_build, w[0].region) < (w[1].base_build, w[1].region)));
+        assert_eq!(build_label(1), "base build 1");
+        assert_eq!(
+            supported_versions(&crate::rules::builtin_rules()),
+            vec!["5.0.15.95687（国际服）、5.0.15.95687（国服）"]
+        );
+        assert!(builds[0].matches_version("5.0.15") && !builds[0].matches_version("5.0.1"));
+
+        let rules = parse_rules("[[rule]]\nid = \"v\"\nsearch = \"00\"\nreplace = \"01\"\nversion = [\"5.0.12\", \"5.0.13\"]").unwrap();
+        assert_eq!(
+            supported_versions(&rules),
+            vec!["5.0.12.91115（国际服）", "5.0.13.92440（国际服，2024-03-26发布）"]
+        );
+    }
+
+    #[test]
+    fn test_load_build_file() {
+        let path = std::env::temp_dir().join(format!("sc2replay-autofix-test-builds-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[[build]]\nbase_build = 4000000001\nversion = \"9.9.9.1\"\nrelease_date = \"2030-01-01\"\n",
+        )
+        .unwrap();
+        assert_eq!(load_build_file(&path).unwrap(), 1);
+        assert_eq!(build_label(4000000001), "9.9.9.1（国际服，2030-01-01发布）");
+
+        // 另一个服务器的条目不会覆盖已有的条目
+        std::fs::write(&path, "[[build]]\nbase_build = 4000000001\nversion = \"9.9.9.1\"\nregion = \"cn\"\n").unwrap();
+        assert_eq!(load_build_file(&path).unwrap(), 1);
+        assert_eq!(build_label(4000000001), "9.9.9.1（国际服，2030-01-01发布）、9.9.9.1（国服）");
+
+        std::fs::write(&path, "[[build]]\nversion = \"9.9.9.1\"\n").unwrap();
+        assert!(load_build_file(&path).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,618 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use dirs::document_dir;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::replay::{read_details, PlayerInfo};
+
+/// SC2文档目录的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sc2DirSource {
+    Documents,     // 系统文档目录（Windows）
+    MacOs,         // ~/Library/Application Support/Blizzard
+    WinePrefixEnv, // WINEPREFIX环境变量指定的前缀
+    Wine,          // 默认Wine前缀 ~/.wine
+    Lutris,        // Lutris游戏前缀
+    Proton,        // Steam Proton的compatdata前缀
+}
+
+impl Sc2DirSource {
+    /// 用于命令行JSON输出的标识
+    pub fn id(&self) -> &'static str {
+        match self {
+            Sc2DirSource::Documents => "documents",
+            Sc2DirSource::MacOs => "macos",
+            Sc2DirSource::WinePrefixEnv => "wineprefix",
+            Sc2DirSource::Wine => "wine",
+            Sc2DirSource::Lutris => "lutris",
+            Sc2DirSource::Proton => "proton",
+        }
+    }
+}
+
+impl fmt::Display for Sc2DirSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Sc2DirSource::Documents => "文档目录",
+            Sc2DirSource::MacOs => "macOS",
+            Sc2DirSource::WinePrefixEnv => "WINEPREFIX",
+            Sc2DirSource::Wine => "Wine",
+            Sc2DirSource::Lutris => "Lutris",
+            Sc2DirSource::Proton => "Proton",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 找到的SC2文档目录及其来源
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sc2BaseDir {
+    pub source: Sc2DirSource,
+    pub path: PathBuf,
+}
+
+/*
+    查找所有SC2文档目录
+    Windows: C:\Users\系统用户名\Documents\StarCraft II\
+    macOS:   ~/Library/Application Support/Blizzard/StarCraft II/
+    Linux:   Wine/Lutris/Proton前缀中的 drive_c/users/<用户>/Documents/StarCraft II/
+*/
+pub fn find_sc2_base_dirs() -> Vec<Sc2BaseDir> {
+    let wine_prefix = std::env::var_os("WINEPREFIX").map(PathBuf::from);
+    find_sc2_base_dirs_in(
+        dirs::home_dir().as_deref(),
+        document_dir().as_deref(),
+        wine_prefix.as_deref(),
+    )
+}
+
+fn find_sc2_base_dirs_in(home: Option<&Path>, documents: Option<&Path>, wine_prefix: Option<&Path>) -> Vec<Sc2BaseDir> {
+    let mut candidates = Vec::new();
+
+    if let Some(documents) = documents {
+        candidates.push((Sc2DirSource::Documents, documents.join("StarCraft II")));
+    }
+
+    if let Some(prefix) = wine_prefix {
+        for dir in wine_prefix_sc2_dirs(prefix) {
+            candidates.push((Sc2DirSource::WinePrefixEnv, dir));
+        }
+    }
+
+    if let Some(home) = home {
+        candidates.push((
+            Sc2DirSource::MacOs,
+            home.join("Library").join("Application Support").join("Blizzard").join("StarCraft II"),
+        ));
+
+        for dir in wine_prefix_sc2_dirs(&home.join(".wine")) {
+            candidates.push((Sc2DirSource::Wine, dir));
+        }
+
+        for prefix in lutris_prefixes(home) {
+            for dir in wine_prefix_sc2_dirs(&prefix) {
+                candidates.push((Sc2DirSource::Lutris, dir));
+            }
+        }
+
+        for prefix in proton_prefixes(home) {
+            for dir in wine_prefix_sc2_dirs(&prefix) {
+                candidates.push((Sc2DirSource::Proton, dir));
+            }
+        }
+    }
+
+    // 只保留存在的目录，并按真实路径去重
+    let mut found: Vec<Sc2BaseDir> = Vec::new();
+    let mut seen = Vec::new();
+    for (source, path) in candidates {
+        if !path.is_dir() {
+            continue;
+        }
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+        found.push(Sc2BaseDir { source, path });
+    }
+    found
+}
+
+// Wine前缀中各用户文档目录下的StarCraft II目录
+fn wine_prefix_sc2_dirs(prefix: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if let Ok(entries) = std::fs::read_dir(prefix.join("drive_c").join("users")) {
+        for entry in entries.flatten() {
+            for documents in ["Documents", "My Documents"] {
+                let dir = entry.path().join(documents).join("StarCraft II");
+                if dir.is_dir() {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+
+    dirs.sort();
+    dirs
+}
+
+// Lutris的游戏前缀：默认的~/Games/*以及游戏配置中的prefix项
+fn lutris_prefixes(home: &Path) -> Vec<PathBuf> {
+    let mut prefixes = Vec::new();
+
+    if let Ok(entries) = std::fs::read_dir(home.join("Games")) {
+        prefixes.extend(entries.flatten().map(|e| e.path()));
+    }
+
+    let config_dir = home.join(".config").join("lutris").join("games");
+    if let Ok(entries) = std::fs::read_dir(config_dir) {
+        for entry in entries.flatten() {
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            for line in content.lines() {
+                if let Some(prefix) = line.trim().strip_prefix("prefix:") {
+                    let prefix = prefix.trim().trim_matches(|c| c == '\'' || c == '"');
+                    if !prefix.is_empty() {
+                        prefixes.push(PathBuf::from(prefix));
+                    }
+                }
+            }
+        }
+    }
+
+    prefixes.sort();
+    prefixes.dedup();
+    prefixes
+}
+
+// Steam Proton的前缀：<Steam>/steamapps/compatdata/<appid>/pfx
+fn proton_prefixes(home: &Path) -> Vec<PathBuf> {
+    let steam_roots = [
+        home.join(".steam").join("steam"),
+        home.join(".local").join("share").join("Steam"),
+        home.join(".var").join("app").join("com.valvesoftware.Steam").join(".local").join("share").join("Steam"),
+    ];
+
+    let mut prefixes = Vec::new();
+    for root in steam_roots {
+        if let Ok(entries) = std::fs::read_dir(root.join("steamapps").join("compatdata")) {
+            prefixes.extend(entries.flatten().map(|e| e.path().join("pfx")));
+        }
+    }
+
+    prefixes.sort();
+    prefixes
+}
+
+/// 返回首选的SC2文档目录，找不到时退回系统文档目录
+pub fn find_sc2_base_dir() -> Option<PathBuf> {
+    find_sc2_base_dirs()
+        .into_iter()
+        .next()
+        .map(|d| d.path)
+        .or_else(|| document_dir().map(|d| d.join("StarCraft II")))
+}
+
+/// 录像类型，对应Replays下的子目录（如Multiplayer）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayCategory {
+    pub folder: String,
+    pub label: String,
+    pub enabled: bool,
+}
+
+impl ReplayCategory {
+    pub fn new(folder: &str, label: &str, enabled: bool) -> Self {
+        Self {
+            folder: folder.to_string(),
+            label: label.to_string(),
+            enabled,
+        }
+    }
+}
+
+/// 默认录像类型，只有天梯/多人录像默认开启
+pub fn default_replay_categories() -> Vec<ReplayCategory> {
+    vec![
+        ReplayCategory::new("Multiplayer", "多人对战", true),
+        ReplayCategory::new("Custom", "自定义游戏", false),
+        ReplayCategory::new("Coop", "合作任务", false),
+        ReplayCategory::new("VersusAI", "人机对战", false),
+    ]
+}
+
+/// 录像类型设置，支持按账户单独开关
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayCategories {
+    pub categories: Vec<ReplayCategory>,
+    /// 键为账户目录（Replays的上级目录），值为该账户启用的类型
+    pub per_account: HashMap<PathBuf, HashSet<String>>,
+}
+
+impl Default for ReplayCategories {
+    fn default() -> Self {
+        Self {
+            categories: default_replay_categories(),
+            per_account: HashMap::new(),
+        }
+    }
+}
+
+impl ReplayCategories {
+    fn default_enabled(&self) -> HashSet<String> {
+        self.categories
+            .iter()
+            .filter(|c| c.enabled)
+            .map(|c| c.folder.clone())
+            .collect()
+    }
+
+    pub fn is_enabled(&self, account_dir: &Path, folder: &str) -> bool {
+        match self.per_account.get(account_dir) {
+            Some(enabled) => enabled.contains(folder),
+            None => self.categories.iter().any(|c| c.enabled && c.folder == folder),
+        }
+    }
+
+    pub fn set_enabled(&mut self, account_dir: &Path, folder: &str, enabled: bool) {
+        let defaults = self.default_enabled();
+        let account = self
+            .per_account
+            .entry(account_dir.to_path_buf())
+            .or_insert(defaults);
+
+        if enabled {
+            account.insert(folder.to_string());
+        } else {
+            account.remove(folder);
+        }
+    }
+}
+
+/// 战网服务器区域，取自toon句柄的第一段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Us,
+    Eu,
+    Kr,
+    Cn,
+    Sea,
+    Ptr,
+    Unknown(u32),
+}
+
+impl Region {
+    pub fn from_id(id: u32) -> Self {
+        match id {
+            1 => Region::Us,
+            2 => Region::Eu,
+            3 => Region::Kr,
+            5 => Region::Cn,
+            6 => Region::Sea,
+            98 => Region::Ptr,
+            other => Region::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Region::Us => write!(f, "US"),
+            Region::Eu => write!(f, "EU"),
+            Region::Kr => write!(f, "KR"),
+            Region::Cn => write!(f, "CN"),
+            Region::Sea => write!(f, "SEA"),
+            Region::Ptr => write!(f, "PTR"),
+            Region::Unknown(id) => write!(f, "区域{}", id),
+        }
+    }
+}
+
+/// toon句柄，形如 5-S2-1-XXXX（区域-程序-服务器-ID）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToonHandle {
+    pub region: Region,
+    pub realm: u32,
+    pub id: u64,
+}
+
+impl ToonHandle {
+    pub fn parse(handle: &str) -> Option<Self> {
+        let parts: Vec<&str> = handle.split('-').collect();
+        if parts.len() != 4 || parts[1] != "S2" {
+            return None;
+        }
+        Some(Self {
+            region: Region::from_id(parts[0].parse().ok()?),
+            realm: parts[2].parse().ok()?,
+            id: parts[3].parse().ok()?,
+        })
+    }
+
+    fn region_id(&self) -> u32 {
+        match self.region {
+            Region::Us => 1,
+            Region::Eu => 2,
+            Region::Kr => 3,
+            Region::Cn => 5,
+            Region::Sea => 6,
+            Region::Ptr => 98,
+            Region::Unknown(id) => id,
+        }
+    }
+
+    /// 是否为录像中的这个玩家
+    pub fn matches(&self, player: &PlayerInfo) -> bool {
+        player.region == self.region_id() && player.realm == self.realm && player.toon_id == self.id
+    }
+}
+
+impl fmt::Display for ToonHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-S2-{}-{}", self.region_id(), self.realm, self.id)
+    }
+}
+
+/// 解析toon句柄，返回区域
+pub fn parse_toon_region(handle: &str) -> Option<Region> {
+    ToonHandle::parse(handle).map(|t| t.region)
+}
+
+/// 一个录像目录，解析自 Accounts/<账户ID>/<toon句柄>/Replays/<类型> 结构
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayLocation {
+    pub account_id: Option<String>,
+    pub toon_handle: Option<String>, // 如 5-S2-1-XXXX
+    pub region: Option<Region>,
+    pub category: String,
+    pub account_dir: PathBuf, // Replays的上级目录，用于按账户设置
+    pub path: PathBuf,
+}
+
+impl ReplayLocation {
+    fn new(accounts_dir: &Path, account_dir: &Path, category: &str, path: PathBuf) -> Self {
+        let components: Vec<String> = account_dir
+            .strip_prefix(accounts_dir)
+            .map(|rel| rel.iter().map(|c| c.to_string_lossy().into_owned()).collect())
+            .unwrap_or_default();
+
+        let account_id = components
+            .first()
+            .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+            .cloned();
+        let toon_handle = components.get(1).filter(|h| parse_toon_region(h).is_some()).cloned();
+        let region = toon_handle.as_deref().and_then(parse_toon_region);
+
+        Self {
+            account_id,
+            toon_handle,
+            region,
+            category: category.to_string(),
+            account_dir: account_dir.to_path_buf(),
+            path,
+        }
+    }
+
+    pub fn toon(&self) -> Option<ToonHandle> {
+        self.toon_handle.as_deref().and_then(ToonHandle::parse)
+    }
+}
+
+/// 从目录中最新的录像读取该账户的玩家名
+pub fn read_player_name(location: &ReplayLocation) -> Option<String> {
+    let toon = location.toon()?;
+    let newest = newest_replay(&location.path)?;
+    let details = read_details(&newest).ok()?;
+
+    details
+        .players
+        .into_iter()
+        .find(|p| toon.matches(p))
+        .map(|p| p.name)
+        .filter(|name| !name.is_empty())
+}
+
+fn newest_replay(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|e| e.path().extension().and_then(|ext| ext.to_str()) == Some("SC2Replay"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// 递归查找基础目录下所有账户的各类型录像目录（不区分是否启用）
+pub fn discover_replay_locations(base_dir: &Path, categories: &[ReplayCategory]) -> Vec<ReplayLocation> {
+    let mut locations = Vec::new();
+    
+    if !base_dir.exists() {
+        return locations;
+    }
+    
+    // 遍历Accounts目录下的所有子目录
+    let accounts_dir = base_dir.join("Accounts");
+    if accounts_dir.exists() {
+        for entry in WalkDir::new(&accounts_dir)
+            .min_depth(1)
+            .max_depth(10)
+            .into_iter()
+            .filter_map(|e| e.ok()) {
+            
+            let path = entry.path();
+            if entry.file_type().is_dir() {
+                // 检查是否包含Replays/<类型>子目录
+                for category in categories {
+                    let replay_path = path.join("Replays").join(&category.folder);
+                    if replay_path.is_dir() {
+                        locations.push(ReplayLocation::new(&accounts_dir, path, &category.folder, replay_path));
+                    }
+                }
+            }
+        }
+    }
+    
+    // 如果没找到，尝试基础目录下的Replays
+    if locations.is_empty() {
+        for category in categories {
+            let default_replay = base_dir.join("Replays").join(&category.folder);
+            if default_replay.is_dir() {
+                locations.push(ReplayLocation::new(&accounts_dir, base_dir, &category.folder, default_replay));
+            }
+        }
+    }
+    
+    locations
+}
+
+/// 在所有找到的SC2文档目录中查找录像目录
+pub fn discover_all_replay_locations(categories: &[ReplayCategory]) -> Vec<ReplayLocation> {
+    find_sc2_base_dirs()
+        .iter()
+        .flat_map(|base| discover_replay_locations(&base.path, categories))
+        .collect()
+}
+
+/// 查找基础目录下所有已启用类型的录像目录
+pub fn find_sc2_replay_dirs(base_dir: &Path, categories: &ReplayCategories) -> Vec<PathBuf> {
+    discover_replay_locations(base_dir, &categories.categories)
+        .into_iter()
+        .filter(|l| categories.is_enabled(&l.account_dir, &l.category))
+        .map(|l| l.path)
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_sc2_base_dir() {
+        let dir = find_sc2_base_dir();
+        println!("找到SC2文档路径: {:?}", dir);
+    
+        if let Some(path) = dir {
+            assert!(path.to_string_lossy().contains("StarCraft II"));
+        }
+    }
+
+    #[test]
+    fn test_find_all_replay_dirs() {
+        let categories = default_replay_categories();
+        let locations = discover_all_replay_locations(&categories);
+        println!("找到的所有录像目录: {:?}", locations);
+        
+        // 验证返回的路径都是传入的某个类型的录像目录
+        for location in locations {
+            assert!(categories.iter().any(|c| c.folder == location.category));
+            assert!(location.path.ends_with(&location.category));
+            assert!(location.path.parent().unwrap().ends_with("Replays"));
+        }
+    }
+
+    #[test]
+    fn test_parse_toon_region() {
+        assert_eq!(parse_toon_region("5-S2-1-7890"), Some(Region::Cn));
+        assert_eq!(parse_toon_region("2-S2-1-1"), Some(Region::Eu));
+        assert_eq!(parse_toon_region("Hotkeys"), None);
+        assert_eq!(parse_toon_region("5-S1-1-7890"), None);
+    }
+
+    #[test]
+    fn test_toon_handle() {
+        let toon = ToonHandle::parse("5-S2-1-7890").unwrap();
+        assert_eq!(toon, ToonHandle { region: Region::Cn, realm: 1, id: 7890 });
+        assert_eq!(toon.to_string(), "5-S2-1-7890");
+
+        let player = PlayerInfo { name: "Alice".to_string(), region: 5, realm: 1, toon_id: 7890 };
+        assert!(toon.matches(&player));
+        assert!(!toon.matches(&PlayerInfo { toon_id: 1, ..player }));
+    }
+
+    #[test]
+    fn test_discover_replay_locations() {
+        let base_dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-locations-{}", std::process::id()));
+        fs::remove_dir_all(&base_dir).ok();
+        let account = base_dir.join("Accounts").join("123456").join("5-S2-1-7890");
+        fs::create_dir_all(account.join("Replays").join("Multiplayer")).unwrap();
+
+        let locations = discover_replay_locations(&base_dir, &default_replay_categories());
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].account_id.as_deref(), Some("123456"));
+        assert_eq!(locations[0].toon_handle.as_deref(), Some("5-S2-1-7890"));
+        assert_eq!(locations[0].region, Some(Region::Cn));
+        assert_eq!(locations[0].category, "Multiplayer");
+        assert_eq!(locations[0].account_dir, account);
+
+        fs::remove_dir_all(&base_dir).ok();
+    }
+
+    #[test]
+    fn test_replay_categories_per_account() {
+        let mut categories = ReplayCategories::default();
+        let account = PathBuf::from("Accounts/1/5-S2-1-1");
+
+        assert!(categories.is_enabled(&account, "Multiplayer"));
+        assert!(!categories.is_enabled(&account, "Custom"));
+
+        categories.set_enabled(&account, "Custom", true);
+        assert!(categories.is_enabled(&account, "Custom"));
+        assert!(categories.is_enabled(&account, "Multiplayer"));
+
+        // 其他账户不受影响
+        assert!(!categories.is_enabled(Path::new("Accounts/2/5-S2-1-2"), "Custom"));
+    }
+
+    #[test]
+    fn test_find_sc2_replay_dirs_by_category() {
+        let base_dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-categories-{}", std::process::id()));
+        fs::remove_dir_all(&base_dir).ok();
+        let account = base_dir.join("Accounts").join("1").join("5-S2-1-1");
+        fs::create_dir_all(account.join("Replays").join("Multiplayer")).unwrap();
+        fs::create_dir_all(account.join("Replays").join("Custom")).unwrap();
+
+        let mut categories = ReplayCategories::default();
+        let found = discover_replay_locations(&base_dir, &categories.categories);
+        assert_eq!(found.len(), 2);
+
+        let dirs = find_sc2_replay_dirs(&base_dir, &categories);
+        assert_eq!(dirs, vec![account.join("Replays").join("Multiplayer")]);
+
+        categories.set_enabled(&account, "Custom", true);
+        assert_eq!(find_sc2_replay_dirs(&base_dir, &categories).len(), 2);
+
+        fs::remove_dir_all(&base_dir).ok();
+    }
+
+    #[test]
+    fn test_find_sc2_base_dirs_in_wine_prefixes() {
+        let home = std::env::temp_dir().join(format!("sc2replay-autofix-test-home-{}", std::process::id()));
+        fs::remove_dir_all(&home).ok();
+        let wine = home.join(".wine").join("drive_c").join("users").join("alice").join("Documents").join("StarCraft II");
+        let proton = home
+            .join(".local").join("share").join("Steam").join("steamapps").join("compatdata").join("123")
+            .join("pfx").join("drive_c").join("users").join("steamuser").join("Documents").join("StarCraft II");
+        std::fs::create_dir_all(&wine).unwrap();
+        std::fs::create_dir_all(&proton).unwrap();
+
+        let found = find_sc2_base_dirs_in(Some(&home), None, None);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0], Sc2BaseDir { source: Sc2DirSource::Wine, path: wine.clone() });
+        assert_eq!(found[1], Sc2BaseDir { source: Sc2DirSource::Proton, path: proton });
+
+        // WINEPREFIX指向同一个前缀时只保留一次
+        let found = find_sc2_base_dirs_in(Some(&home), None, Some(&home.join(".wine")));
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].source, Sc2DirSource::WinePrefixEnv);
+
+        std::fs::remove_dir_all(&home).ok();
+    }
+}
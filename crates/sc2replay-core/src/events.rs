@@ -0,0 +1,113 @@
+//! 核心库向调用方报告的事件
+//!
+//! 调用方创建一个[`EventBus`]，交给修复和监控使用，需要事件的地方各自调用
+//! [`EventBus::subscribe`]得到一个接收端。没有订阅者时事件被丢弃。
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use crate::fixer::FixOutcome;
+use crate::log::{LogLevel, LogRecord, LogSource};
+
+/// 录像目录的变化
+#[derive(Debug, Clone, PartialEq)]
+pub enum DirectoryChange {
+    /// 选择了新的SC2基础目录
+    BaseDir(PathBuf),
+    /// 添加录像目录，包括监控过程中自动发现的目录
+    Added(PathBuf),
+    /// 移除录像目录
+    Removed(PathBuf),
+}
+
+/// 事件总线上的事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// 一条日志
+    Log(LogRecord),
+    /// 开始修复一个录像
+    FixStarted(PathBuf),
+    /// 一个录像修复结束，失败时为错误信息
+    FixCompleted { path: PathBuf, outcome: Result<FixOutcome, String> },
+    /// 监控启动、停止、暂停或恢复
+    MonitorStateChanged { running: bool, paused: bool },
+    /// 录像目录发生变化
+    DirectoriesChanged(DirectoryChange),
+    /// 批量修复进度
+    Progress { done: usize, total: usize },
+}
+
+/// 事件总线，克隆后共享同一组订阅者
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 新增一个订阅者，之后发布的事件都会发给它；接收端被丢弃后自动退订
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = unbounded();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(sender);
+        }
+        receiver
+    }
+
+    /// 把事件发给所有订阅者
+    pub fn publish(&self, event: Event) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|s| s.send(event.clone()).is_ok());
+        }
+    }
+
+    /// 发布一条日志
+    pub fn log(&self, record: LogRecord) {
+        self.publish(Event::Log(record));
+    }
+
+    pub fn debug(&self, source: LogSource, message: impl Into<String>) {
+        self.log(LogRecord::new(LogLevel::Debug, source, message));
+    }
+
+    pub fn info(&self, source: LogSource, message: impl Into<String>) {
+        self.log(LogRecord::new(LogLevel::Info, source, message));
+    }
+
+    pub fn warn(&self, source: LogSource, message: impl Into<String>) {
+        self.log(LogRecord::new(LogLevel::Warn, source, message));
+    }
+
+    pub fn error(&self, source: LogSource, message: impl Into<String>) {
+        self.log(LogRecord::new(LogLevel::Error, source, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiple_subscribers() {
+        let bus = EventBus::new();
+        bus.info(LogSource::App, "没有订阅者");
+
+        let first = bus.subscribe();
+        let second = bus.clone().subscribe();
+        let record = LogRecord::new(LogLevel::Warn, LogSource::Monitor, "a");
+        bus.log(record.clone());
+        assert_eq!(first.try_recv(), Ok(Event::Log(record.clone())));
+        assert_eq!(second.try_recv(), Ok(Event::Log(record)));
+        assert!(first.try_recv().is_err());
+
+        // 丢弃的接收端不再接收事件
+        drop(first);
+        bus.publish(Event::Progress { done: 1, total: 2 });
+        assert_eq!(second.try_recv(), Ok(Event::Progress { done: 1, total: 2 }));
+        assert_eq!(bus.subscribers.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,827 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::events::{Event, EventBus};
+use crate::log::{outcome, LogLevel, LogRecord, LogSource};
+use crate::journal::{self, sha256_file, sha256_hex, JournalAction, JournalEntry};
+use crate::mpq::{UserDataHeader, USER_DATA_HEADER_SIZE};
+use crate::patch::{PatchChange, PatchScope, ReplayEditor};
+use crate::rules::{builtin_rules, format_hex, PatchRule};
+
+/// 对被选中的目录下的录像进行修复
+pub fn batch_fix_dir(dir: &Path, options: &FixOptions, bus: &EventBus) -> anyhow::Result<()> {
+    let files = replay_files_in(dir)?;
+    let total = files.len();
+    bus.publish(Event::Progress { done: 0, total });
+
+    for (i, path) in files.iter().enumerate() {
+        // 结果已由fix_single_file记入日志
+        let _ = fix_single_file(path, options, bus);
+        bus.publish(Event::Progress { done: i + 1, total });
+    }
+
+    Ok(())
+}
+
+/// 分别选中所有账户的录像目录
+pub fn batch_fix_dirs(dirs: &[PathBuf], options: &FixOptions, bus: &EventBus) -> anyhow::Result<()> {
+    let mut any_error = false;
+
+    // 先列出所有录像，便于报告总进度
+    let mut batches = Vec::new();
+    for dir in dirs.iter().filter(|d| d.exists()) {
+        // 显式处理错误，确保即使一个目录失败也能继续处理其他目录
+        match replay_files_in(dir) {
+            Ok(files) => batches.push((dir, files)),
+            Err(e) => {
+                bus.log(
+                    LogRecord::new(LogLevel::Error, LogSource::Fixer, format!("处理目录时出错: {}", e)).with_path(dir),
+                );
+                any_error = true;
+            }
+        }
+    }
+
+    let total = batches.iter().map(|(_, files)| files.len()).sum();
+    let mut done = 0;
+    bus.publish(Event::Progress { done, total });
+
+    for (dir, files) in batches {
+        bus.log(LogRecord::new(LogLevel::Debug, LogSource::Fixer, "进入录像目录").with_path(dir));
+        for path in &files {
+            let _ = fix_single_file(path, options, bus);
+            done += 1;
+            bus.publish(Event::Progress { done, total });
+        }
+    }
+
+    // 如果有任何错误，返回错误信息
+    if any_error {
+        Err(anyhow::anyhow!("部分目录修复失败，请查看日志详情"))
+    } else {
+        Ok(())
+    }
+}
+
+// 目录下（不含子目录）的所有录像
+fn replay_files_in(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Err(anyhow::anyhow!("目录不存在: {}", dir.display()));
+    }
+
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).min_depth(1).max_depth(1) {
+        let path = entry?.into_path();
+        if path.extension().and_then(|e| e.to_str()) == Some("SC2Replay") {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// 修复常量
+pub const SEARCH_BYTES: &[u8] = &[0x09, 0x00, 0x04, 0x09, 0x00, 0x06, 0x09, 0x00];
+pub const TARGET_BYTES: &[u8] = &[0x09, 0x0A, 0x04, 0x09, 0x00, 0x06, 0x09, 0x1E];
+pub const SCAN_LIMIT: usize = 128;  // 前128字节
+pub const TARGET_BASE_BUILD: u32 = 95687;  // 内置规则修正到的版本
+
+/// 默认输出文件名模板，{stem}为原文件名（不含扩展名）
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "{stem}-FIXED";
+
+/// 修复后文件的输出方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    #[default]
+    Suffix,  // 按模板生成新文件，原文件不变
+    InPlace, // 覆盖原文件，原文件备份为 .SC2Replay.bak
+}
+
+/// 修复选项，由设置文件加载，调用方各自持有并传给修复函数
+#[derive(Debug, Clone)]
+pub struct FixOptions {
+    pub output_mode: OutputMode,
+    pub output_template: String,
+    pub rules: Vec<PatchRule>,
+    /// 修复记录文件，None时不记录
+    pub journal: Option<PathBuf>,
+}
+
+impl Default for FixOptions {
+    fn default() -> Self {
+        Self {
+            output_mode: OutputMode::Suffix,
+            output_template: DEFAULT_OUTPUT_TEMPLATE.to_string(),
+            rules: builtin_rules(),
+            journal: None,
+        }
+    }
+}
+
+/// 修复时读取录像的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadMode {
+    /// 规则都只涉及录像开头时流式处理，否则读入内存
+    #[default]
+    Auto,
+    /// 只读入录像开头，其余部分由系统直接复制（支持时为reflink或copy_file_range）
+    Streaming,
+    /// 整个录像读入内存
+    InMemory,
+}
+
+/// 单个文件的修复结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixOutcome {
+    Fixed { rule_id: String, output_path: PathBuf },
+    SkippedOutput, // 本工具生成的文件，跳过
+    NoMatch,       // 未找到目标字节序列
+}
+
+// 修复单个文件，通过事件总线报告开始和结果，每个文件记一条带路径和结果的日志
+pub fn fix_single_file(input_path: &Path, options: &FixOptions, bus: &EventBus) -> anyhow::Result<()>{
+    bus.publish(Event::FixStarted(input_path.to_path_buf()));
+    let result = fix_file(input_path, options);
+    bus.publish(Event::FixCompleted {
+        path: input_path.to_path_buf(),
+        outcome: result.as_ref().cloned().map_err(|e| format!("{:#}", e)),
+    });
+
+    let record = |level, message: String, result: &str| {
+        LogRecord::new(level, LogSource::Fixer, message).with_path(input_path).with_outcome(result)
+    };
+    match result {
+        Ok(FixOutcome::Fixed { rule_id, output_path }) => {
+            bus.log(record(
+                LogLevel::Info,
+                format!("修复成功({}): {}", rule_id, output_path.display()),
+                outcome::FIXED,
+            ));
+            Ok(())
+        }
+        Ok(FixOutcome::SkippedOutput) => {
+            bus.log(record(LogLevel::Debug, "跳过本工具生成的文件".to_string(), outcome::SKIPPED));
+            Ok(())
+        }
+        Ok(FixOutcome::NoMatch) => {
+            let message = "未找到目标字节序列，可能录像能够正常工作";
+            bus.log(record(LogLevel::Warn, message.to_string(), outcome::NO_MATCH));
+            Err(anyhow::anyhow!(message))
+        }
+        Err(e) => {
+            bus.log(record(LogLevel::Error, format!("修复失败: {:#}", e), outcome::FAILED));
+            Err(e)
+        }
+    }
+}
+
+/// 修复单个文件并返回结果，不写日志；结果记入修复记录
+pub fn fix_file(input_path: &Path, options: &FixOptions) -> anyhow::Result<FixOutcome> {
+    fix_file_with(input_path, options, ReadMode::Auto)
+}
+
+/// 按指定的选项和读取方式修复单个文件，结果记入修复记录
+pub fn fix_file_with(input_path: &Path, options: &FixOptions, mode: ReadMode) -> anyhow::Result<FixOutcome> {
+    let mut entry = JournalEntry::new(JournalAction::Failed, input_path);
+    let result = fix_file_recorded(input_path, options, mode, &mut entry);
+    entry.action = match &result {
+        Ok(FixOutcome::Fixed { .. }) => JournalAction::Fixed,
+        Ok(FixOutcome::SkippedOutput) => JournalAction::Skipped,
+        Ok(FixOutcome::NoMatch) => JournalAction::NoMatch,
+        Err(e) => {
+            entry.error = Some(format!("{:#}", e));
+            JournalAction::Failed
+        }
+    };
+
+    // 写入修复记录时每个结果都带输入哈希；流式处理未修复、跳过或失败时还没有计算，这里读取文件计算
+    if options.journal.is_some() && entry.input_hash.is_none() {
+        entry.input_hash = sha256_file(input_path).ok();
+    }
+
+    // 修改过的文件必须有记录，其他结果记录失败时不影响返回值
+    let recorded = journal::record(options.journal.as_deref(), &entry);
+    if entry.action == JournalAction::Fixed {
+        recorded.context("修复已完成，但无法写入修复记录")?;
+    }
+    result
+}
+
+// 修复单个文件，把哈希、规则和输出填入entry
+fn fix_file_recorded(
+    input_path: &Path,
+    options: &FixOptions,
+    mode: ReadMode,
+    entry: &mut JournalEntry,
+) -> anyhow::Result<FixOutcome> {
+    // 跳过名称后面为-FIXED的录像，因为这表示此录像已经被修复
+    if is_output_file(input_path, &options.output_template){
+        return Ok(FixOutcome::SkippedOutput)
+    }
+    
+    // 检验文件类型
+    if input_path.extension().and_then(|e| e.to_str()) != Some("SC2Replay"){
+        return Err(anyhow::anyhow!(format!("此文件不是SC2Replay文件，文件为{}", input_path.display())));
+    }
+
+    // 如果是录像文件则读取，流式处理时只读入开头
+    let mut file = File::open(input_path)
+        .with_context(|| format!("无法打开文件: {}", input_path.display() ))?;
+    let prefix_len = match mode {
+        ReadMode::InMemory => None,
+        ReadMode::Auto => streaming_prefix_len(&mut file, &options.rules)?,
+        ReadMode::Streaming => Some(
+            streaming_prefix_len(&mut file, &options.rules)?
+                .context("有规则需要读取整个录像，无法流式修复")?,
+        ),
+    };
+    let mut data = Vec::new();
+    match prefix_len {
+        Some(len) => (&mut file).take(len as u64).read_to_end(&mut data)?,
+        None => file.read_to_end(&mut data)?,
+    };
+    drop(file);
+    let original_prefix = prefix_len.map(|_| data.clone());
+    // 只有写入修复记录时才计算哈希；读入整个文件时在修改前计算，流式处理时在复制过程中计算
+    let hash = options.journal.is_some();
+    if hash && original_prefix.is_none() {
+        entry.input_hash = Some(sha256_hex(&data));
+    }
+
+    // 依次尝试各条规则，第一条适用的规则修改录像
+    let mut replay = ReplayEditor::new(data);
+    let matched = apply_rules(&mut replay, &options.rules)?;
+    let Some((rule, change)) = matched else {
+        return Ok(FixOutcome::NoMatch);
+    };
+    entry.rule_id = Some(rule.id.clone());
+    entry.archive_file = change.archive_file;
+    entry.offset = Some(change.offset);
+    entry.bytes_before = Some(format_hex(&change.before));
+    entry.bytes_after = Some(format_hex(&change.after));
+    let data = replay.into_bytes();
+
+    // 生成输出路径
+    let output_path = output_path(input_path, options);
+
+    // 覆盖原文件前先备份
+    let backup_path = backup_path(input_path);
+    if options.output_mode == OutputMode::InPlace {
+        fs::copy(input_path, &backup_path)
+            .with_context(|| format!("无法备份文件: {}", backup_path.display()))?;
+    }
+
+    // 写入修复后的文件
+    entry.output_path = Some(output_path.clone());
+    let Some(original_prefix) = original_prefix else {
+        File::create(&output_path)
+            .with_context(|| format!("无法创建文件: {}", output_path.display()))?
+            .write_all(&data)?;
+        if hash {
+            entry.output_hash = Some(sha256_hex(&data));
+        }
+        return Ok(FixOutcome::Fixed { rule_id: rule.id.clone(), output_path });
+    };
+
+    // 流式处理：其余部分来自原文件，覆盖模式下原文件已被改写，从备份读取
+    let source = match options.output_mode {
+        OutputMode::Suffix => input_path,
+        OutputMode::InPlace => backup_path.as_path(),
+    };
+    let hashes = write_streamed(source, &output_path, options.output_mode, &original_prefix, &data, hash)
+        .with_context(|| format!("无法写入文件: {}", output_path.display()))?;
+    if let Some((input_hash, output_hash)) = hashes {
+        entry.input_hash = Some(input_hash);
+        entry.output_hash = Some(output_hash);
+    }
+
+    Ok(FixOutcome::Fixed { rule_id: rule.id.clone(), output_path })
+}
+
+// 流式修复需要读入的开头长度：规则的查找范围，以及到归档头为止的录像头（改变长度时需要）
+// 有规则需要读取整个录像时为None
+fn streaming_prefix_len(file: &mut File, rules: &[PatchRule]) -> anyhow::Result<Option<usize>> {
+    let mut len = USER_DATA_HEADER_SIZE;
+    for rule in rules {
+        match rule.patch.scope() {
+            PatchScope::Prefix(limit) => len = len.max(limit),
+            PatchScope::Header => {}
+            PatchScope::Archive => return Ok(None),
+        }
+    }
+
+    let mut head = Vec::new();
+    (&mut *file).take(USER_DATA_HEADER_SIZE as u64).read_to_end(&mut head)?;
+    file.rewind()?;
+    if let Ok(header) = UserDataHeader::parse(&head) {
+        len = len.max(header.archive_offset as usize);
+    }
+    Ok(Some(len))
+}
+
+// 写入流式修复的结果：prefix为修改后的开头，其余部分从source中original之后复制
+// hash为true时在复制的同时计算原文件和修复后文件的SHA-256
+fn write_streamed(
+    source: &Path,
+    output_path: &Path,
+    mode: OutputMode,
+    original: &[u8],
+    prefix: &[u8],
+    hash: bool,
+) -> anyhow::Result<Option<(String, String)>> {
+    // 长度不变时只改写开头：覆盖模式直接改原文件，否则先整体复制
+    let same_len = prefix.len() == original.len();
+    if same_len && !hash {
+        if mode == OutputMode::Suffix {
+            fs::copy(source, output_path)?;
+        }
+        OpenOptions::new().write(true).open(output_path)?.write_all(prefix)?;
+        return Ok(None);
+    }
+
+    let mut rest = File::open(source)?;
+    rest.seek(SeekFrom::Start(original.len() as u64))?;
+    let copy_rest = !(same_len && mode == OutputMode::InPlace);
+    let mut output = if copy_rest {
+        File::create(output_path)?
+    } else {
+        OpenOptions::new().write(true).open(output_path)?
+    };
+    output.write_all(prefix)?;
+    if !hash {
+        // 文件之间复制，Linux上由copy_file_range完成，不经过用户空间
+        io::copy(&mut rest, &mut output)?;
+        return Ok(None);
+    }
+
+    // 其余部分两个文件相同，读一遍同时计算两者的哈希
+    let mut input_hash = Sha256::new();
+    let mut output_hash = Sha256::new();
+    input_hash.update(original);
+    output_hash.update(prefix);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = rest.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        input_hash.update(&buf[..n]);
+        output_hash.update(&buf[..n]);
+        if copy_rest {
+            output.write_all(&buf[..n])?;
+        }
+    }
+    Ok(Some((format!("{:x}", input_hash.finalize()), format!("{:x}", output_hash.finalize()))))
+}
+
+/// 依次尝试各条规则，应用第一条适用的规则并返回其修改
+///
+/// 限定了base build或版本的规则只用于录像头中版本相符的录像（见[`PatchRule::applies_to`]），录像头无法解析时不限定。
+pub fn apply_rules<'a>(
+    replay: &mut ReplayEditor,
+    rules: &'a [PatchRule],
+) -> anyhow::Result<Option<(&'a PatchRule, PatchChange)>> {
+    let base_build = replay.base_build();
+    for rule in rules {
+        if let Some(build) = base_build
+            && !rule.applies_to(build)
+        {
+            continue;
+        }
+        let change = rule
+            .patch
+            .apply(replay)
+            .with_context(|| format!("规则{}应用失败", rule.id))?;
+        if let Some(change) = change {
+            return Ok(Some((rule, change)));
+        }
+    }
+    Ok(None)
+}
+
+/// 找到第一条适用的规则及其修改，不修改data
+pub fn find_rule<'a>(data: &[u8], rules: &'a [PatchRule]) -> anyhow::Result<Option<(&'a PatchRule, PatchChange)>> {
+    apply_rules(&mut ReplayEditor::new(data.to_vec()), rules)
+}
+
+// 查找字节序列偏移
+#[cfg(test)]
+fn find_bytes_offset(data: &[u8], search: &[u8]) -> Option<usize> {
+    crate::patch::find_bytes_within(data, search, SCAN_LIMIT)
+}
+
+/// 是否为按template生成的修复后文件
+pub fn is_output_file(path: &Path, template: &str) -> bool {
+    let stem = path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+    let (prefix, suffix) = template.split_once("{stem}").unwrap_or(("", "-FIXED"));
+    if prefix.is_empty() && suffix.is_empty() {
+        return false;
+    }
+    stem.starts_with(prefix) && stem.ends_with(suffix) && stem.len() > prefix.len() + suffix.len()
+}
+
+/// 按选项得到修复后的文件路径
+pub fn output_path(input_path: &Path, options: &FixOptions) -> PathBuf {
+    match options.output_mode {
+        OutputMode::Suffix => generate_output_path_with(input_path, &options.output_template),
+        OutputMode::InPlace => input_path.to_path_buf(),
+    }
+}
+
+/// 覆盖模式下原文件的备份路径
+pub fn backup_path(input_path: &Path) -> PathBuf {
+    let mut name = input_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    input_path.with_file_name(name)
+}
+
+/// 由按template生成的修复后文件路径得到原文件路径，不是修复后的文件时返回None
+pub fn original_path(output_path: &Path, template: &str) -> Option<PathBuf> {
+    if !is_output_file(output_path, template) {
+        return None;
+    }
+    let stem = output_path.file_stem()?.to_str()?;
+    let (prefix, suffix) = template.split_once("{stem}").unwrap_or(("", "-FIXED"));
+    let original = stem.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    Some(output_path.with_file_name(format!("{}.SC2Replay", original)))
+}
+
+/// 撤销修复时执行的操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum RevertAction {
+    RestoreBackup { backup: PathBuf, original: PathBuf }, // 用.bak备份覆盖回原文件
+    RemoveOutput { output: PathBuf },                     // 删除生成的修复文件
+}
+
+/// 撤销目录下的修复：恢复.bak备份，删除原文件仍在的修复文件；dry_run时只返回将执行的操作
+///
+/// 修复文件按options的输出模板识别，执行的操作记入options指定的修复记录。
+pub fn revert_dir(dir: &Path, dry_run: bool, options: &FixOptions) -> anyhow::Result<Vec<RevertAction>> {
+    if !dir.exists() {
+        return Err(anyhow::anyhow!("目录不存在: {}", dir.display()));
+    }
+
+    let mut actions = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).min_depth(1).max_depth(1).sort_by_file_name() {
+        let path = entry?.into_path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+        if let Some(original_name) = name.strip_suffix(".SC2Replay.bak") {
+            let original = path.with_file_name(format!("{}.SC2Replay", original_name));
+            actions.push(RevertAction::RestoreBackup { backup: path, original });
+        } else if path.extension().and_then(|e| e.to_str()) == Some("SC2Replay") {
+            // 只有原文件还在时才删除，避免删掉唯一的一份录像
+            if original_path(&path, &options.output_template).is_some_and(|original| original.exists()) {
+                actions.push(RevertAction::RemoveOutput { output: path });
+            }
+        }
+    }
+
+    if !dry_run {
+        for action in &actions {
+            // 在恢复或删除前计算哈希，之后仍能按内容查到
+            let entry = match action {
+                RevertAction::RestoreBackup { backup, original } => {
+                    let mut entry = JournalEntry::new(JournalAction::Reverted, backup);
+                    entry.input_hash = sha256_file(backup).ok();
+                    entry.output_path = Some(original.clone());
+                    entry.output_hash = entry.input_hash.clone();
+                    fs::rename(backup, original).with_context(|| format!("无法恢复备份: {}", backup.display()))?;
+                    entry
+                }
+                RevertAction::RemoveOutput { output } => {
+                    let mut entry = JournalEntry::new(JournalAction::Reverted, output);
+                    entry.input_hash = sha256_file(output).ok();
+                    fs::remove_file(output).with_context(|| format!("无法删除文件: {}", output.display()))?;
+                    entry
+                }
+            };
+            journal::record(options.journal.as_deref(), &entry)?;
+        }
+    }
+
+    Ok(actions)
+}
+
+// 生成修复后的文件路径
+#[cfg(test)]
+fn generate_output_path(input_path: &Path) -> PathBuf {
+    generate_output_path_with(input_path, DEFAULT_OUTPUT_TEMPLATE)
+}
+
+fn generate_output_path_with(input_path: &Path, template: &str) -> PathBuf {
+    let stem = input_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or("fixed");
+    let parent = input_path.parent().unwrap_or(Path::new("."));
+    parent.join(format!("{}.SC2Replay", template.replace("{stem}", stem)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+
+    #[test]
+    fn test_find_bytes_offset() {
+        // 测试字节序列查找
+        let data = vec![0x00, 0x01, 0x09, 0x00, 0x04, 0x09, 0x00, 0x06, 0x09, 0x00, 0x02];
+        let offset = find_bytes_offset(&data, SEARCH_BYTES);
+        
+        assert_eq!(offset, Some(2));
+    }
+
+    #[test]
+    fn test_generate_output_path() {
+        let input_path = PathBuf::from("test.SC2Replay");
+        let output_path = generate_output_path(&input_path);
+        
+        assert_eq!(output_path, PathBuf::from("test-FIXED.SC2Replay"));
+    }
+
+    #[test]
+    fn test_output_template() {
+        let input_path = PathBuf::from("dir/test.SC2Replay");
+        let output_path = generate_output_path_with(&input_path, "fixed_{stem}");
+
+        assert_eq!(output_path, PathBuf::from("dir/fixed_test.SC2Replay"));
+        assert!(is_output_file(&output_path, "fixed_{stem}"));
+        assert!(!is_output_file(&input_path, "fixed_{stem}"));
+        assert!(is_output_file(Path::new("test-FIXED.SC2Replay"), DEFAULT_OUTPUT_TEMPLATE));
+    }
+
+    #[test]
+    fn test_find_bytes_offset_short_data() {
+        assert_eq!(find_bytes_offset(&[0x09, 0x00], SEARCH_BYTES), None);
+    }
+
+    #[test]
+    fn test_rule_base_build() {
+        // 录像头{1: {1: 0, 2: 0, 3: 0, 4: build, 5: build}}，版本号为0的部分正是内置规则查找的字节
+        let replay = |build: i64| {
+            let mut header = vec![0x05, 0x02, 0x02, 0x05, 0x0A, 0x02, 0x09, 0x00, 0x04, 0x09, 0x00, 0x06, 0x09, 0x00];
+            for tag in [0x08, 0x0A] {
+                header.extend_from_slice(&[tag, 0x09]);
+                header.extend(crate::replay::encode_vint(build));
+            }
+            crate::mpq::tests::build_archive(&header, "replay.details", b"details")
+        };
+
+        // 内置规则不限定版本
+        let rules = builtin_rules();
+        assert!(find_rule(&replay(TARGET_BASE_BUILD as i64), &rules).unwrap().is_some());
+        assert!(find_rule(&replay(95841), &rules).unwrap().is_some());
+
+        let gated = |extra: &str| {
+            let text = format!("[[rule]]\nid = \"gated\"\nsearch = \"{}\"\nreplace = \"{}\"\n{}", format_hex(SEARCH_BYTES), format_hex(TARGET_BYTES), extra);
+            crate::rules::parse_rules(&text).unwrap()
+        };
+        let rules = gated("base_build = [95687]");
+        assert!(find_rule(&replay(TARGET_BASE_BUILD as i64), &rules).unwrap().is_some());
+        assert!(find_rule(&replay(95841), &rules).unwrap().is_none());
+        // 按版本表匹配版本号，表中没有的base build不适用
+        let rules = gated("version = [\"5.0.15\"]");
+        assert!(find_rule(&replay(TARGET_BASE_BUILD as i64), &rules).unwrap().is_some());
+        assert!(find_rule(&replay(93333), &rules).unwrap().is_none());
+        assert!(find_rule(&replay(95841), &rules).unwrap().is_none());
+        // 录像头无法解析时不按版本限定
+        assert!(find_rule(SEARCH_BYTES, &rules).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_revert_dir() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-revert-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.SC2Replay", "a-FIXED.SC2Replay", "b-FIXED.SC2Replay", "c.SC2Replay.bak"] {
+            File::create(dir.join(name)).unwrap().write_all(name.as_bytes()).unwrap();
+        }
+
+        let planned = revert_dir(&dir, true, &FixOptions::default()).unwrap();
+        assert_eq!(planned.len(), 2);
+        assert!(dir.join("a-FIXED.SC2Replay").exists());
+
+        revert_dir(&dir, false, &FixOptions::default()).unwrap();
+        assert!(!dir.join("a-FIXED.SC2Replay").exists());
+        // 原文件不存在的修复文件保留
+        assert!(dir.join("b-FIXED.SC2Replay").exists());
+        assert_eq!(fs::read(dir.join("c.SC2Replay")).unwrap(), b"c.SC2Replay.bak");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_fix_file_journal() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-fix-journal-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let journal_path = dir.join("journal.jsonl");
+        let options = FixOptions { journal: Some(journal_path.clone()), ..FixOptions::default() };
+
+        let input = dir.join("a.SC2Replay");
+        let mut replay = b"jrnl".to_vec();
+        replay.extend_from_slice(SEARCH_BYTES);
+        fs::write(&input, &replay).unwrap();
+        let FixOutcome::Fixed { output_path, .. } = fix_file_with(&input, &options, ReadMode::Auto).unwrap() else {
+            panic!("应当修复");
+        };
+
+        let entries = journal::read_journal(&journal_path).unwrap();
+        let found = journal::entries_for_file(&entries, &output_path);
+        assert_eq!(found.len(), 1);
+        let entry = found[0];
+        assert_eq!(entry.action, JournalAction::Fixed);
+        assert_eq!(entry.offset, Some(4));
+        assert_eq!(entry.bytes_before.as_deref(), Some(format_hex(SEARCH_BYTES).as_str()));
+        assert_eq!(entry.input_hash, Some(sha256_hex(&replay)));
+        assert_eq!(entry.output_hash, sha256_file(&output_path).ok());
+
+        // 跳过和无需修复的结果也记录输入哈希
+        let untouched = dir.join("b.SC2Replay");
+        fs::write(&untouched, b"nothing to fix").unwrap();
+        fix_file_with(&output_path, &options, ReadMode::InMemory).unwrap();
+        fix_file_with(&untouched, &options, ReadMode::InMemory).unwrap();
+        let entries = journal::read_journal(&journal_path).unwrap();
+        assert_eq!(entries[1].action, JournalAction::Skipped);
+        assert_eq!(entries[1].input_hash, entry.output_hash);
+        assert_eq!(entries[2].action, JournalAction::NoMatch);
+        assert_eq!(entries[2].input_hash, Some(sha256_hex(b"nothing to fix")));
+
+        // 撤销也有记录
+        revert_dir(&dir, false, &options).unwrap();
+        let entries = journal::read_journal(&journal_path).unwrap();
+        assert!(entries.iter().any(|e| e.action == JournalAction::Reverted && e.input_path == output_path));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_streaming_matches_in_memory() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-streaming-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("a.SC2Replay");
+        let mut header = b"stream".to_vec();
+        header.extend_from_slice(SEARCH_BYTES);
+        let details: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let replay = crate::mpq::tests::build_archive(&header, "replay.details", &details);
+
+        // 等长替换和使录像头变长的替换
+        let mut longer = TARGET_BYTES.to_vec();
+        longer.extend_from_slice(&[0; 600]);
+        let rule_sets = [
+            builtin_rules(),
+            vec![PatchRule {
+                id: "longer".to_string(),
+                description: String::new(),
+                patch: std::sync::Arc::new(crate::patch::ReplaceBytes {
+                    search: SEARCH_BYTES.to_vec(),
+                    replace: longer,
+                    scan_limit: SCAN_LIMIT,
+                }),
+                base_builds: Vec::new(),
+                versions: Vec::new(),
+            }],
+        ];
+
+        let journal_path = dir.join("journal.jsonl");
+        for rules in rule_sets {
+            for output_mode in [OutputMode::Suffix, OutputMode::InPlace] {
+                let options = FixOptions {
+                    output_mode,
+                    rules: rules.clone(),
+                    journal: Some(journal_path.clone()),
+                    ..FixOptions::default()
+                };
+                let mut outputs = Vec::new();
+                for mode in [ReadMode::InMemory, ReadMode::Streaming] {
+                    fs::write(&input, &replay).unwrap();
+                    let FixOutcome::Fixed { output_path, .. } = fix_file_with(&input, &options, mode).unwrap() else {
+                        panic!("应当修复");
+                    };
+                    outputs.push(fs::read(&output_path).unwrap());
+                    // 流式处理时在复制的同时计算哈希
+                    let entries = journal::read_journal(&journal_path).unwrap();
+                    let entry = entries.last().unwrap();
+                    assert_eq!(entry.input_hash, Some(sha256_hex(&replay)));
+                    assert_eq!(entry.output_hash, Some(sha256_hex(outputs.last().unwrap())));
+                    if output_mode == OutputMode::InPlace {
+                        assert_eq!(fs::read(backup_path(&input)).unwrap(), replay);
+                        fs::remove_file(backup_path(&input)).unwrap();
+                    }
+                }
+                assert_eq!(outputs[0], outputs[1]);
+                let archive = crate::mpq::MpqArchive::from_bytes(outputs.remove(0)).unwrap();
+                assert_eq!(archive.read_file("replay.details").unwrap(), details);
+            }
+        }
+
+        // 需要整个录像的规则不能流式处理，Auto时读入内存
+        let options = FixOptions {
+            rules: vec![PatchRule {
+                id: "details".to_string(),
+                description: String::new(),
+                patch: std::sync::Arc::new(crate::patch::ReplaceInFile {
+                    file: "replay.details".to_string(),
+                    search: vec![0, 1, 2],
+                    replace: vec![2, 1, 0],
+                }),
+                base_builds: Vec::new(),
+                versions: Vec::new(),
+            }],
+            ..FixOptions::default()
+        };
+        fs::write(&input, &replay).unwrap();
+        assert!(fix_file_with(&input, &options, ReadMode::Streaming).is_err());
+        assert!(matches!(fix_file_with(&input, &options, ReadMode::Auto), Ok(FixOutcome::Fixed { .. })));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    // 无需修复的录像在流式处理时只读入开头；写入修复记录时为计算哈希需要读整个文件
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_no_match_reads_prefix_only() {
+        // 当前线程通过read等系统调用读取的字节数
+        fn thread_read_bytes() -> u64 {
+            let io = fs::read_to_string("/proc/thread-self/io").unwrap();
+            io.lines().find_map(|l| l.strip_prefix("rchar: ")).unwrap().parse().unwrap()
+        }
+
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-no-match-read-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("a.SC2Replay");
+        let details = vec![0x5A; 4 << 20];
+        fs::write(&input, crate::mpq::tests::build_archive(TARGET_BYTES, "replay.details", &details)).unwrap();
+
+        let before = thread_read_bytes();
+        assert!(matches!(fix_file_with(&input, &FixOptions::default(), ReadMode::Streaming), Ok(FixOutcome::NoMatch)));
+        let read = thread_read_bytes() - before;
+        // 开头到归档头为止0x400字节，另有读取/proc本身的几百字节
+        assert!(read < 0x2000, "读取了{}字节", read);
+
+        // 写入修复记录时无需修复的录像也记录输入哈希
+        let journal_path = dir.join("journal.jsonl");
+        let options = FixOptions { journal: Some(journal_path.clone()), ..FixOptions::default() };
+        assert!(matches!(fix_file_with(&input, &options, ReadMode::Streaming), Ok(FixOutcome::NoMatch)));
+        let entries = journal::read_journal(&journal_path).unwrap();
+        assert_eq!(entries[0].action, JournalAction::NoMatch);
+        assert_eq!(entries[0].input_hash, Some(sha256_hex(&fs::read(&input).unwrap())));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_batch_fix_dir_events() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-batch-events-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let mut replay = SEARCH_BYTES.to_vec();
+        replay.extend_from_slice(&[0u8; 16]);
+        fs::write(dir.join("a.SC2Replay"), &replay).unwrap();
+        fs::write(dir.join("b.SC2Replay"), [0u8; 16]).unwrap();
+
+        let bus = EventBus::new();
+        let events = bus.subscribe();
+        batch_fix_dir(&dir, &FixOptions::default(), &bus).unwrap();
+        let events: Vec<Event> = events.try_iter().collect();
+
+        let outcomes: Vec<&Result<FixOutcome, String>> = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::FixCompleted { outcome, .. } => Some(outcome),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().any(|o| matches!(o, Ok(FixOutcome::Fixed { .. }))));
+        assert!(outcomes.contains(&&Ok(FixOutcome::NoMatch)));
+        assert_eq!(events.iter().filter(|e| matches!(e, Event::FixStarted(_))).count(), 2);
+
+        // 每个文件一条带路径和结果的日志
+        let records: Vec<&LogRecord> = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Log(record) if record.outcome.is_some() => Some(record),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().any(|r| r.level == LogLevel::Info && r.outcome.as_deref() == Some(outcome::FIXED)));
+        assert!(records.iter().any(|r| r.level == LogLevel::Warn && r.outcome.as_deref() == Some(outcome::NO_MATCH)));
+
+        let progress: Vec<&Event> = events.iter().filter(|e| matches!(e, Event::Progress { .. })).collect();
+        assert_eq!(progress.first(), Some(&&Event::Progress { done: 0, total: 2 }));
+        assert_eq!(progress.last(), Some(&&Event::Progress { done: 2, total: 2 }));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,220 @@
+//! 修复记录
+//!
+//! 每次修复、跳过、失败和撤销都向记录文件追加一行JSON，包括修复或撤销的原文件和输出文件的SHA-256、
+//! 使用的规则、偏移以及替换前后的字节。录像出现问题时可以据此确认它是否被本工具修改过、用的是哪条规则。
+//! 记录文件由调用方在[`FixOptions::journal`](crate::fixer::FixOptions::journal)中指定，未指定时不记录。
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Context;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::log::{format_timestamp, now_ms};
+
+lazy_static! {
+    // 同一进程内的写入依次进行，每条记录一次写入一整行
+    static ref WRITE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// 记录的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalAction {
+    Fixed,
+    /// 本工具生成的文件，未处理
+    Skipped,
+    /// 没有匹配的规则
+    NoMatch,
+    Failed,
+    /// 撤销修复：恢复备份或删除修复文件
+    Reverted,
+}
+
+impl JournalAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            JournalAction::Fixed => "修复",
+            JournalAction::Skipped => "跳过",
+            JournalAction::NoMatch => "未匹配",
+            JournalAction::Failed => "失败",
+            JournalAction::Reverted => "撤销",
+        }
+    }
+}
+
+impl fmt::Display for JournalAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// 一条修复记录，未涉及的字段为空
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix时间戳，毫秒
+    pub timestamp_ms: u64,
+    pub action: JournalAction,
+    /// 被处理的文件；撤销时为被恢复的备份或被删除的修复文件
+    pub input_path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
+    /// 修改的是归档内的文件时为其文件名，如"replay.details"，此时offset为该文件内的偏移
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// 替换前的字节，格式同规则文件，如"09 00 04"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_after: Option<String>,
+    /// 修复后的文件；撤销恢复备份时为被覆盖的原文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JournalEntry {
+    pub fn new(action: JournalAction, input_path: &Path) -> Self {
+        Self {
+            timestamp_ms: now_ms(),
+            action,
+            input_path: input_path.to_path_buf(),
+            input_hash: None,
+            rule_id: None,
+            archive_file: None,
+            offset: None,
+            bytes_before: None,
+            bytes_after: None,
+            output_path: None,
+            output_hash: None,
+            error: None,
+        }
+    }
+
+    /// 是否与文件有关：路径相同，或内容的SHA-256与原文件/输出文件相同
+    pub fn concerns(&self, path: &Path, hash: Option<&str>) -> bool {
+        self.input_path == path
+            || self.output_path.as_deref() == Some(path)
+            || hash.is_some_and(|hash| {
+                self.input_hash.as_deref() == Some(hash) || self.output_hash.as_deref() == Some(hash)
+            })
+    }
+}
+
+/// 如"2026-01-02 03:04:05 [修复] a.SC2Replay 规则builtin @16 09 00 -> 09 0A => a-FIXED.SC2Replay"
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}] {}", format_timestamp(self.timestamp_ms), self.action, self.input_path.display())?;
+        if let Some(rule_id) = &self.rule_id {
+            write!(f, " 规则{}", rule_id)?;
+        }
+        if let Some(file) = &self.archive_file {
+            write!(f, " {}", file)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " @{}", offset)?;
+        }
+        if let (Some(before), Some(after)) = (&self.bytes_before, &self.bytes_after) {
+            write!(f, " {} -> {}", before, after)?;
+        }
+        if let Some(output_path) = &self.output_path {
+            write!(f, " => {}", output_path.display())?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " ({})", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// 向记录文件追加一条记录，path为None时不做任何事
+pub fn record(path: Option<&Path>, entry: &JournalEntry) -> anyhow::Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let _guard = WRITE_LOCK.lock();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("无法创建目录: {}", dir.display()))?;
+    }
+
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("无法写入修复记录: {}", path.display()))
+}
+
+/// 读取记录文件，文件不存在时为空；无法解析的行被跳过
+pub fn read_journal(path: &Path) -> anyhow::Result<Vec<JournalEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("无法读取修复记录: {}", path.display()))?;
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// 与文件有关的记录，文件存在时同时按内容匹配，改名或移动过的文件也能找到
+pub fn entries_for_file<'a>(entries: &'a [JournalEntry], path: &Path) -> Vec<&'a JournalEntry> {
+    let hash = sha256_file(path).ok();
+    entries.iter().filter(|e| e.concerns(path, hash.as_deref())).collect()
+}
+
+/// 数据的SHA-256，小写十六进制
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// 文件内容的SHA-256，小写十六进制
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_query() {
+        let dir = std::env::temp_dir().join(format!("sc2replay-autofix-test-journal-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let journal = dir.join("journal.jsonl");
+        assert!(read_journal(&journal).unwrap().is_empty());
+
+        // 修复后的文件被改名，仍能按内容找到
+        let renamed = dir.join("renamed.SC2Replay");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&renamed, b"fixed").unwrap();
+
+        let mut fixed = JournalEntry::new(JournalAction::Fixed, &dir.join("a.SC2Replay"));
+        fixed.rule_id = Some("builtin".to_string());
+        fixed.output_path = Some(dir.join("a-FIXED.SC2Replay"));
+        fixed.output_hash = Some(sha256_hex(b"fixed"));
+        let failed = JournalEntry::new(JournalAction::Failed, &dir.join("b.SC2Replay"));
+        record(Some(&journal), &fixed).unwrap();
+        record(Some(&journal), &failed).unwrap();
+
+        let entries = read_journal(&journal).unwrap();
+        assert_eq!(entries, vec![fixed.clone(), failed]);
+        assert_eq!(entries_for_file(&entries, &renamed), vec![&fixed]);
+        assert_eq!(entries_for_file(&entries, &dir.join("a-FIXED.SC2Replay")), vec![&fixed]);
+        assert!(entries_for_file(&entries, &dir.join("c.SC2Replay")).is_empty());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
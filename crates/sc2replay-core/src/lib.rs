@@ -0,0 +1,52 @@
+//! SC2录像修复的核心库，不依赖任何界面
+//!
+//! 图形界面和命令行都建立在这个库之上，其他工具也可以直接使用：
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use sc2replay_core::events::{Event, EventBus};
+//! use sc2replay_core::fixer::{batch_fix_dir, fix_file, FixOptions, FixOutcome};
+//!
+//! // 修复选项由调用方持有，默认为内置规则、生成新文件、不写修复记录
+//! let options = FixOptions::default();
+//! match fix_file(Path::new("Game.SC2Replay"), &options) {
+//!     Ok(FixOutcome::Fixed { output_path, .. }) => println!("{}", output_path.display()),
+//!     Ok(_) => println!("无需修复"),
+//!     Err(e) => eprintln!("{:#}", e),
+//! }
+//!
+//! // 批量修复和监控通过事件总线报告日志和进度
+//! let bus = EventBus::new();
+//! let events = bus.subscribe();
+//! batch_fix_dir(Path::new("Replays/Multiplayer"), &options, &bus).ok();
+//! for event in events.try_iter() {
+//!     if let Event::Log(record) = event {
+//!         eprintln!("{}", record);
+//!     }
+//! }
+//! ```
+
+/// SC2版本表：base build对应的版本号、发布日期和服务器，可由用户文件补充
+pub mod builds;
+/// 录像目录的查找：SC2文档目录、账户和录像类型
+pub mod discovery;
+/// 核心库向调用方报告的事件
+pub mod events;
+/// 录像修复：规则匹配、输出路径和撤销
+pub mod fixer;
+/// 修复记录：每次修复、跳过、失败和撤销的原文件、规则和输出
+pub mod journal;
+/// 结构化日志记录：级别、来源、路径和修复结果
+pub mod log;
+/// 录像目录监控和自动修复
+pub mod monitor;
+/// SC2录像使用的MPQ归档格式
+pub mod mpq;
+/// 修复操作：字节替换、录像头字段和归档文件改写，改变长度时重新计算MPQ偏移
+pub mod patch;
+/// 录像头和replay.details的解析
+pub mod replay;
+/// 修复规则及规则文件
+pub mod rules;
+/// 由样本录像推导修复规则和测试用例（开发用）
+pub mod signature;
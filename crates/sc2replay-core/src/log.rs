@@ -0,0 +1,199 @@
+//! 结构化日志记录
+//!
+//! 修复、监控和目录查找的日志都是一条[`LogRecord`]，带有时间、级别、来源以及相关的录像路径和结果，
+//! 通过[`crate::events::EventBus`]发布。界面按级别和来源过滤，日志文件每行保存一条记录的JSON。
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+/// 日志级别，从低到高
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "调试",
+            LogLevel::Info => "信息",
+            LogLevel::Warn => "警告",
+            LogLevel::Error => "错误",
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// 产生日志的模块
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    Fixer,
+    Monitor,
+    Discovery,
+    /// 设置、配置、自启动等应用本身的操作
+    App,
+}
+
+impl LogSource {
+    pub const ALL: [LogSource; 4] = [LogSource::Fixer, LogSource::Monitor, LogSource::Discovery, LogSource::App];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogSource::Fixer => "修复",
+            LogSource::Monitor => "监控",
+            LogSource::Discovery => "目录",
+            LogSource::App => "应用",
+        }
+    }
+}
+
+impl fmt::Display for LogSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// 修复结果在日志记录中的取值
+pub mod outcome {
+    pub const FIXED: &str = "fixed";
+    pub const SKIPPED: &str = "skipped";
+    pub const NO_MATCH: &str = "no_match";
+    pub const FAILED: &str = "failed";
+}
+
+/// 一条日志
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// Unix时间戳，毫秒
+    pub timestamp_ms: u64,
+    pub level: LogLevel,
+    pub source: LogSource,
+    pub message: String,
+    /// 相关的录像或目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// 修复结果，取值见[`outcome`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+}
+
+impl LogRecord {
+    /// 以当前时间创建一条日志
+    pub fn new(level: LogLevel, source: LogSource, message: impl Into<String>) -> Self {
+        Self {
+            timestamp_ms: now_ms(),
+            level,
+            source,
+            message: message.into(),
+            path: None,
+            outcome: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn with_outcome(mut self, outcome: &str) -> Self {
+        self.outcome = Some(outcome.to_string());
+        self
+    }
+
+    /// 日志内容，有路径时附在后面
+    pub fn text(&self) -> String {
+        match &self.path {
+            Some(path) => format!("{} ({})", self.message, path.display()),
+            None => self.message.clone(),
+        }
+    }
+
+    /// 记录时间，UTC，如"2026-01-02 03:04:05"
+    pub fn time_string(&self) -> String {
+        format_timestamp(self.timestamp_ms)
+    }
+}
+
+/// 如"2026-01-02 03:04:05 [错误][修复] 修复失败: ... (a.SC2Replay)"
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}][{}] {}", self.time_string(), self.level, self.source, self.text())
+    }
+}
+
+/// 当前的Unix毫秒时间戳
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 把Unix毫秒时间戳格式化为UTC时间
+pub fn format_timestamp(timestamp_ms: u64) -> String {
+    let secs = timestamp_ms / 1000;
+    let (days, rest) = (secs / 86400, secs % 86400);
+
+    // 公历日期换算，见 http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400_000), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_767_323_045_999), "2026-01-02 03:04:05");
+    }
+
+    #[test]
+    fn test_record_json_round_trip() {
+        let record = LogRecord::new(LogLevel::Error, LogSource::Fixer, "修复失败")
+            .with_path(Path::new("a.SC2Replay"))
+            .with_outcome(outcome::FAILED);
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains("\"level\":\"error\""));
+        assert!(json.contains("\"source\":\"fixer\""));
+        assert_eq!(serde_json::from_str::<LogRecord>(&json).unwrap(), record);
+
+        // 没有路径和结果时省略这两个字段
+        let json = serde_json::to_string(&LogRecord::new(LogLevel::Info, LogSource::App, "a")).unwrap();
+        assert!(!json.contains("path") && !json.contains("outcome"));
+        assert!(LogLevel::Debug < LogLevel::Error);
+    }
+}
@@ -0,0 +1,745 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::collections::{HashSet, HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::{Mutex, RwLock};
+use walkdir::WalkDir;
+
+use crate::events::{DirectoryChange, Event, EventBus};
+use crate::log::{LogLevel, LogRecord, LogSource};
+use crate::discovery::{find_sc2_replay_dirs, ReplayCategories};
+use crate::fixer::{fix_single_file, is_output_file, output_path, FixOptions};
+
+// 文件大小和修改时间，用于判断文件是否发生变化
+type FileSignature = (u64, Option<SystemTime>);
+
+/// 默认静默时间，同一文件在此时间内没有新事件才开始修复
+pub const DEFAULT_QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+pub struct MonitorInstance {
+    // 整个监控实例的停止标志（账户目录监控线程使用）
+    stop_flag: Arc<AtomicBool>,
+    // 正在监控的目录及其各自的停止标志
+    watchers: Arc<Mutex<HashMap<PathBuf, Arc<AtomicBool>>>>,
+    // 用户手动移除的目录，自动发现时跳过
+    removed_dirs: Arc<Mutex<HashSet<PathBuf>>>,
+    // 自动发现时使用的录像类型设置
+    categories: Arc<Mutex<ReplayCategories>>,
+    // 运行统计
+    stats: Arc<MonitorStats>,
+    // 按文件合并的待处理事件
+    coalescer: Arc<Coalescer>,
+    // 合并静默时间（毫秒）
+    quiet_period_ms: Arc<AtomicU64>,
+    // 暂停标志，暂停时继续监控和排队但不修复
+    paused: Arc<AtomicBool>,
+    // 已过静默时间、等待修复的任务
+    backlog: Arc<Mutex<VecDeque<(PathBuf, Instant)>>>,
+    // 修复选项，运行中可以更新
+    options: Arc<RwLock<FixOptions>>,
+    // 日志、修复结果和状态变化发布到这里
+    bus: EventBus,
+}
+
+// 监控运行统计，由各监控线程和修复任务更新
+#[derive(Default)]
+struct MonitorStats {
+    events_seen: AtomicU64,
+    jobs_running: AtomicU64,
+    jobs_completed: AtomicU64,
+    jobs_failed: AtomicU64,
+    total_latency_ms: AtomicU64,
+    last_event: Mutex<Option<SystemTime>>,
+}
+
+impl MonitorStats {
+    fn record_event(&self) {
+        self.events_seen.fetch_add(1, Ordering::SeqCst);
+        *self.last_event.lock().unwrap() = Some(SystemTime::now());
+    }
+
+    fn record_job_finished(&self, success: bool, detected_at: Instant) {
+        self.jobs_running.fetch_sub(1, Ordering::SeqCst);
+        if success {
+            self.jobs_completed.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.jobs_failed.fetch_add(1, Ordering::SeqCst);
+        }
+        let latency = detected_at.elapsed().as_millis() as u64;
+        self.total_latency_ms.fetch_add(latency, Ordering::SeqCst);
+    }
+}
+
+/// 监控状态快照，供界面显示和导出
+#[derive(Debug, Clone, Default)]
+pub struct MonitorSnapshot {
+    pub running: bool,
+    pub paused: bool,
+    pub dirs_watched: usize,
+    pub events_seen: u64,
+    pub jobs_queued: u64,
+    pub jobs_running: u64,
+    pub jobs_completed: u64,
+    pub jobs_failed: u64,
+    pub last_event: Option<SystemTime>,
+    /// 从发现新录像到修复结束的平均耗时
+    pub avg_fix_latency: Option<Duration>,
+}
+
+impl MonitorSnapshot {
+    /// 距离最近一次文件事件的时间
+    pub fn last_event_ago(&self) -> Option<Duration> {
+        self.last_event.and_then(|t| t.elapsed().ok())
+    }
+
+    /// 由to_json导出的JSON还原，用于显示守护进程的监控状态
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        let count = |key: &str| value.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+        let millis = |key: &str| value.get(key).and_then(|v| v.as_u64()).map(Duration::from_millis);
+        Some(Self {
+            running: value.get("running")?.as_bool()?,
+            paused: value.get("paused").and_then(|v| v.as_bool()).unwrap_or(false),
+            dirs_watched: count("dirs_watched") as usize,
+            events_seen: count("events_seen"),
+            jobs_queued: count("jobs_queued"),
+            jobs_running: count("jobs_running"),
+            jobs_completed: count("jobs_completed"),
+            jobs_failed: count("jobs_failed"),
+            last_event: millis("last_event_ago_ms").and_then(|ago| SystemTime::now().checked_sub(ago)),
+            avg_fix_latency: millis("avg_fix_latency_ms"),
+        })
+    }
+
+    /// 导出为JSON，时间均以毫秒表示
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "running": self.running,
+            "paused": self.paused,
+            "dirs_watched": self.dirs_watched,
+            "events_seen": self.events_seen,
+            "jobs_queued": self.jobs_queued,
+            "jobs_running": self.jobs_running,
+            "jobs_completed": self.jobs_completed,
+            "jobs_failed": self.jobs_failed,
+            "last_event_ago_ms": self.last_event_ago().map(|d| d.as_millis() as u64),
+            "avg_fix_latency_ms": self.avg_fix_latency.map(|d| d.as_millis() as u64),
+        })
+    }
+}
+
+impl fmt::Display for MonitorSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match (self.running, self.paused) {
+            (false, _) => "已停止",
+            (true, true) => "已暂停",
+            (true, false) => "运行中",
+        };
+        writeln!(f, "状态: {}", state)?;
+        writeln!(f, "监控目录: {}", self.dirs_watched)?;
+        writeln!(f, "文件事件: {}", self.events_seen)?;
+        writeln!(
+            f,
+            "任务: 排队{} 执行中{} 完成{} 失败{}",
+            self.jobs_queued, self.jobs_running, self.jobs_completed, self.jobs_failed
+        )?;
+        match self.last_event_ago() {
+            Some(ago) => writeln!(f, "最近事件: {}秒前", ago.as_secs())?,
+            None => writeln!(f, "最近事件: 无")?,
+        }
+        match self.avg_fix_latency {
+            Some(latency) => write!(f, "平均修复耗时: {}ms", latency.as_millis()),
+            None => write!(f, "平均修复耗时: 无"),
+        }
+    }
+}
+
+impl MonitorInstance {
+    fn new(options: FixOptions, bus: EventBus) -> Self {
+        Self {
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            watchers: Arc::new(Mutex::new(HashMap::new())),
+            removed_dirs: Arc::new(Mutex::new(HashSet::new())),
+            categories: Arc::new(Mutex::new(ReplayCategories::default())),
+            stats: Arc::new(MonitorStats::default()),
+            coalescer: Arc::new(Coalescer::default()),
+            quiet_period_ms: Arc::new(AtomicU64::new(DEFAULT_QUIET_PERIOD.as_millis() as u64)),
+            paused: Arc::new(AtomicBool::new(false)),
+            backlog: Arc::new(Mutex::new(VecDeque::new())),
+            options: Arc::new(RwLock::new(options)),
+            bus,
+        }
+    }
+
+    /// 更新修复选项，之后的修复任务使用新选项
+    pub fn set_fix_options(&self, options: FixOptions) {
+        *self.options.write().unwrap() = options;
+    }
+
+    fn fix_options(&self) -> FixOptions {
+        self.options.read().unwrap().clone()
+    }
+
+    fn publish_state(&self) {
+        self.bus.publish(Event::MonitorStateChanged { running: self.is_running(), paused: self.is_paused() });
+    }
+
+    /// 暂停修复，目录监控和事件排队继续进行
+    pub fn pause(&self) {
+        if !self.paused.swap(true, Ordering::SeqCst) {
+            self.bus.info(LogSource::Monitor, "⏸ 监控已暂停，新录像将在恢复后修复");
+            self.publish_state();
+        }
+    }
+
+    /// 恢复修复，处理暂停期间积压的任务
+    pub fn resume(&self) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            let pending = self.backlog.lock().unwrap().len() + self.coalescer.len();
+            self.bus.info(LogSource::Monitor, format!("▶ 监控已恢复，待处理任务{}个", pending));
+            self.publish_state();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// 设置合并静默时间
+    pub fn set_quiet_period(&self, quiet: Duration) {
+        self.quiet_period_ms.store(quiet.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    // 启动任务分发线程，把合并后静默的事件交给修复器
+    fn spawn_dispatcher(&self) -> anyhow::Result<()> {
+        let instance = self.clone();
+
+        std::thread::Builder::new()
+            .name("monitor-dispatcher".to_string())
+            .spawn(move || {
+                while instance.is_running() {
+                    std::thread::sleep(Duration::from_millis(200));
+
+                    let quiet = Duration::from_millis(instance.quiet_period_ms.load(Ordering::SeqCst));
+                    let ready = instance.coalescer.take_ready(quiet);
+                    instance.backlog.lock().unwrap().extend(ready);
+
+                    // 暂停期间任务留在积压队列中，恢复后按顺序处理
+                    while instance.is_running() && !instance.is_paused() {
+                        let job = instance.backlog.lock().unwrap().pop_front();
+                        match job {
+                            Some((path, detected_at)) => {
+                                run_fix_job(&path, detected_at, &instance)
+                            }
+                            None => break,
+                        }
+                    }
+                }
+            })?;
+
+        Ok(())
+    }
+
+    /// 获取当前运行统计
+    pub fn snapshot(&self) -> MonitorSnapshot {
+        let stats = &self.stats;
+        let completed = stats.jobs_completed.load(Ordering::SeqCst);
+        let failed = stats.jobs_failed.load(Ordering::SeqCst);
+        let finished = completed + failed;
+
+        MonitorSnapshot {
+            running: self.is_running(),
+            dirs_watched: self.watchers.lock().unwrap().len(),
+            events_seen: stats.events_seen.load(Ordering::SeqCst),
+            paused: self.is_paused(),
+            jobs_queued: (self.coalescer.len() + self.backlog.lock().unwrap().len()) as u64,
+            jobs_running: stats.jobs_running.load(Ordering::SeqCst),
+            jobs_completed: completed,
+            jobs_failed: failed,
+            last_event: *stats.last_event.lock().unwrap(),
+            avg_fix_latency: (finished > 0)
+                .then(|| Duration::from_millis(stats.total_latency_ms.load(Ordering::SeqCst) / finished)),
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+
+        // 停止所有监控线程
+        for flag in self.watchers.lock().unwrap().values() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        
+        // 清理待处理任务
+        self.coalescer.clear();
+        self.backlog.lock().unwrap().clear();
+        self.bus.debug(LogSource::Monitor, "🛑 任务队列已清空，不再接受新任务");
+        
+        // 等待监控线程退出
+        std::thread::sleep(Duration::from_millis(100));
+        
+        self.bus.info(LogSource::Monitor, "🛑 所有监控已停止，任务已清理");
+        self.publish_state();
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.stop_flag.load(Ordering::SeqCst)
+    }
+
+    /// 当前正在监控的目录
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self.watchers.lock().unwrap().keys().cloned().collect();
+        dirs.sort();
+        dirs
+    }
+
+    /// 在监控运行期间添加目录，已在监控中则返回false
+    pub fn add_dir(&self, dir: PathBuf) -> anyhow::Result<bool> {
+        if !self.is_running() {
+            return Err(anyhow::anyhow!("监控已停止，无法添加目录"));
+        }
+
+        let mut watchers = self.watchers.lock().unwrap();
+        if watchers.contains_key(&dir) {
+            return Ok(false);
+        }
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        spawn_dir_watcher(dir.clone(), stop_flag.clone(), self)?;
+        watchers.insert(dir.clone(), stop_flag);
+        self.removed_dirs.lock().unwrap().remove(&dir);
+        Ok(true)
+    }
+
+    /// 在监控运行期间移除目录，之后的自动发现也不会再加入该目录
+    ///
+    /// 该目录下还未修复的任务一并取消。
+    pub fn remove_dir(&self, dir: &Path) -> bool {
+        self.removed_dirs.lock().unwrap().insert(dir.to_path_buf());
+
+        let removed = match self.watchers.lock().unwrap().remove(dir) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        };
+
+        // 先停止监控线程再清理，之后不会再有该目录的新任务
+        let cancelled = self.coalescer.remove_under(dir) + {
+            let mut backlog = self.backlog.lock().unwrap();
+            let before = backlog.len();
+            backlog.retain(|(path, _)| !path.starts_with(dir));
+            before - backlog.len()
+        };
+        if cancelled > 0 {
+            self.bus.log(
+                LogRecord::new(LogLevel::Info, LogSource::Monitor, format!("目录已移除，取消{}个待修复任务", cancelled))
+                    .with_path(dir),
+            );
+        }
+        removed
+    }
+
+    /// 更新自动发现使用的录像类型设置
+    pub fn set_categories(&self, categories: ReplayCategories) {
+        *self.categories.lock().unwrap() = categories;
+    }
+
+    /// 监控Accounts目录，新账户或新赛季目录出现时自动加入监控
+    ///
+    /// 与录像目录使用同样的监控线程，只有Accounts下的目录结构发生变化时才重新查找录像目录。
+    pub fn watch_accounts(&self, base_dir: PathBuf, categories: ReplayCategories) -> anyhow::Result<()> {
+        self.set_categories(categories);
+        let accounts = base_dir.join("Accounts");
+        let instance = self.clone();
+        let discovery_base = base_dir.clone();
+
+        spawn_watcher(
+            "monitor-accounts".to_string(),
+            accounts,
+            self.stop_flag.clone(),
+            LogSource::Discovery,
+            &self.bus,
+            scan_account_dirs,
+            move |_| instance.add_discovered_dirs(&discovery_base),
+        )?;
+
+        // 监控线程只报告之后的变化，启动时先查找一次
+        self.add_discovered_dirs(&base_dir);
+        Ok(())
+    }
+
+    // 查找base_dir下的录像目录，把新出现的目录加入监控，用户移除过的目录跳过
+    fn add_discovered_dirs(&self, base_dir: &Path) {
+        let categories = self.categories.lock().unwrap().clone();
+        for dir in find_sc2_replay_dirs(base_dir, &categories) {
+            if !self.is_running() {
+                break;
+            }
+            if self.removed_dirs.lock().unwrap().contains(&dir) {
+                continue;
+            }
+
+            match self.add_dir(dir.clone()) {
+                Ok(true) => {
+                    self.bus.log(LogRecord::new(LogLevel::Info, LogSource::Discovery, "发现新录像目录").with_path(&dir));
+                    self.bus.publish(Event::DirectoriesChanged(DirectoryChange::Added(dir)));
+                }
+                Ok(false) => {}
+                Err(e) => self.bus.log(
+                    LogRecord::new(LogLevel::Error, LogSource::Monitor, format!("无法监控目录: {}", e)).with_path(&dir),
+                ),
+            }
+        }
+    }
+}
+
+/// 监控多个目录，按options修复，日志和修复结果发布到bus
+pub fn start_watch_multiple(dirs: Vec<PathBuf>, options: FixOptions, bus: &EventBus) -> anyhow::Result<MonitorInstance> {
+    let instance = MonitorInstance::new(options, bus.clone());
+    instance.spawn_dispatcher()?;
+
+    for dir in dirs {
+        instance.add_dir(dir)?;
+    }
+
+    instance.publish_state();
+    Ok(instance)
+}
+
+// 为单个目录启动监控线程，发现新文件或文件变化时交给合并层
+fn spawn_dir_watcher(dir: PathBuf, stop_flag: Arc<AtomicBool>, instance: &MonitorInstance) -> anyhow::Result<()> {
+    let stats = instance.stats.clone();
+    let coalescer = instance.coalescer.clone();
+    let options = instance.options.clone();
+    let name = format!("monitor-{}", dir.file_name().unwrap_or_default().to_str().unwrap_or("dir"));
+
+    spawn_watcher(name, dir, stop_flag, LogSource::Monitor, &instance.bus, scan_dir, move |changed| {
+        for (path, signature) in changed {
+            stats.record_event();
+            if !is_ignored_path(path, &options.read().unwrap().output_template) {
+                coalescer.touch(path.clone(), *signature);
+            }
+        }
+    })
+}
+
+// 监控线程：每秒用scan列出dir下的条目，与上次相比新增或变化的条目交给on_change
+// stop_flag置位后退出，之后不再调用on_change
+fn spawn_watcher(
+    name: String,
+    dir: PathBuf,
+    stop_flag: Arc<AtomicBool>,
+    source: LogSource,
+    bus: &EventBus,
+    scan: fn(&Path, &mut HashMap<PathBuf, FileSignature>),
+    on_change: impl Fn(&[(PathBuf, FileSignature)]) + Send + 'static,
+) -> anyhow::Result<()> {
+    let bus = bus.clone();
+
+    std::thread::Builder::new()
+        .name(name)
+        .spawn(move || {
+            bus.log(LogRecord::new(LogLevel::Info, source, "开始监控目录").with_path(&dir));
+            let mut known_files = HashMap::new();
+
+            scan(&dir, &mut known_files);
+
+            while !stop_flag.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_secs(1));
+
+                // 检查是否已停止
+                if stop_flag.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let mut current_files = HashMap::new();
+                scan(&dir, &mut current_files);
+
+                // 新条目或大小/修改时间发生变化的条目都视为一次事件
+                let changed: Vec<(PathBuf, FileSignature)> = current_files
+                    .iter()
+                    .filter(|(path, signature)| known_files.get(*path) != Some(*signature))
+                    .map(|(path, signature)| (path.clone(), *signature))
+                    .collect();
+                if !changed.is_empty() && !stop_flag.load(Ordering::SeqCst) {
+                    on_change(&changed);
+                }
+
+                known_files = current_files;
+            }
+
+            bus.log(LogRecord::new(LogLevel::Debug, source, "🛑 监控线程退出").with_path(&dir));
+        })?;
+
+    Ok(())
+}
+
+// 忽略非录像文件、按输出模板template生成的文件以及临时文件
+fn is_ignored_path(path: &Path, template: &str) -> bool {
+    if path.extension().and_then(|e| e.to_str()) != Some("SC2Replay") {
+        return true;
+    }
+    if is_output_file(path, template) {
+        return true;
+    }
+
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name.starts_with('.') || name.starts_with('~')
+}
+
+// 按文件合并短时间内的多次事件，静默一段时间后才生成一次修复任务
+#[derive(Default)]
+struct Coalescer {
+    pending: Mutex<HashMap<PathBuf, PendingJob>>,
+    // 本工具写入的文件及写入后的状态，对应的事件直接忽略（覆盖原文件模式）
+    own_writes: Mutex<HashMap<PathBuf, FileSignature>>,
+}
+
+struct PendingJob {
+    first_seen: Instant,
+    last_seen: Instant,
+}
+
+impl Coalescer {
+    fn touch(&self, path: PathBuf, signature: FileSignature) {
+        {
+            let mut own_writes = self.own_writes.lock().unwrap();
+            if own_writes.get(&path) == Some(&signature) {
+                own_writes.remove(&path);
+                return;
+            }
+        }
+
+        let now = Instant::now();
+        self.pending
+            .lock()
+            .unwrap()
+            .entry(path)
+            .and_modify(|job| job.last_seen = now)
+            .or_insert(PendingJob {
+                first_seen: now,
+                last_seen: now,
+            });
+    }
+
+    // 取出已静默超过quiet的任务，返回路径和首次发现时间
+    fn take_ready(&self, quiet: Duration) -> Vec<(PathBuf, Instant)> {
+        let mut pending = self.pending.lock().unwrap();
+        let ready: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, job)| job.last_seen.elapsed() >= quiet)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        ready
+            .into_iter()
+            .filter_map(|path| pending.remove(&path).map(|job| (path, job.first_seen)))
+            .collect()
+    }
+
+    // 记录本工具刚写入的文件
+    fn record_own_write(&self, path: &Path) {
+        if let Ok(metadata) = std::fs::metadata(path) {
+            let signature = (metadata.len(), metadata.modified().ok());
+            self.own_writes.lock().unwrap().insert(path.to_path_buf(), signature);
+        }
+    }
+
+    // 取消dir下的所有待处理任务，返回取消的数量
+    fn remove_under(&self, dir: &Path) -> usize {
+        let mut pending = self.pending.lock().unwrap();
+        let before = pending.len();
+        pending.retain(|path, _| !path.starts_with(dir));
+        before - pending.len()
+    }
+
+    fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+// 执行一次修复任务
+fn run_fix_job(path: &Path, detected_at: Instant, instance: &MonitorInstance) {
+    let stats = &instance.stats;
+    let bus = &instance.bus;
+
+    // 合并期间文件可能已被删除或移走
+    if !path.exists() {
+        bus.log(LogRecord::new(LogLevel::Warn, LogSource::Monitor, "文件已不存在，取消修复").with_path(path));
+        return;
+    }
+
+    stats.jobs_running.fetch_add(1, Ordering::SeqCst);
+    // 结果已由fix_single_file记入日志
+    let options = instance.fix_options();
+    let result = fix_single_file(path, &options, bus);
+    stats.record_job_finished(result.is_ok(), detected_at);
+
+    if result.is_ok() {
+        instance.coalescer.record_own_write(&output_path(path, &options));
+    }
+}
+
+pub fn start_watch_async(dir: PathBuf, options: FixOptions, bus: &EventBus) -> anyhow::Result<MonitorInstance> {
+    start_watch_multiple(vec![dir], options, bus)
+}
+
+fn scan_dir(dir: &Path, files: &mut HashMap<PathBuf, FileSignature>) {
+    // 不在这里检查是否停止，让调用方负责检查
+    
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Ok(metadata) = entry.metadata()
+                && metadata.is_file()
+            {
+                files.insert(path, (metadata.len(), metadata.modified().ok()));
+            }
+        }
+    }
+}
+
+// 列出Accounts下的目录及其修改时间，不进入Replays下的录像目录
+// 新建的账户或录像类型目录会改变父目录的修改时间或增加条目
+fn scan_account_dirs(accounts: &Path, dirs: &mut HashMap<PathBuf, FileSignature>) {
+    let walker = WalkDir::new(accounts).min_depth(1).max_depth(10).into_iter().filter_entry(|e| {
+        e.file_type().is_dir() && e.path().parent().and_then(|p| p.file_name()) != Some("Replays".as_ref())
+    });
+    for entry in walker.filter_map(|e| e.ok()) {
+        if let Ok(metadata) = entry.metadata() {
+            dirs.insert(entry.into_path(), (0, metadata.modified().ok()));
+        }
+    }
+}
+
+/// 兼容原有接口
+pub fn start_watch(
+    dir: PathBuf,
+    options: FixOptions,
+    stop_rx: crossbeam_channel::Receiver<()>,
+    bus: &EventBus,
+) -> anyhow::Result<MonitorInstance> {
+    let instance = start_watch_async(dir, options, bus)?;
+    let instance_clone = instance.clone();
+    
+    std::thread::spawn(move || {
+        let _ = stop_rx.recv();
+        instance_clone.stop();
+    });
+    
+    Ok(instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_stats() {
+        let instance = MonitorInstance::new(FixOptions::default(), EventBus::new());
+        let stats = &instance.stats;
+
+        stats.record_event();
+        stats.jobs_running.fetch_add(2, Ordering::SeqCst);
+        stats.record_job_finished(true, Instant::now());
+        stats.record_job_finished(false, Instant::now());
+
+        let snapshot = instance.snapshot();
+        assert!(snapshot.running);
+        assert_eq!(snapshot.events_seen, 1);
+        assert_eq!(snapshot.jobs_running, 0);
+        assert_eq!(snapshot.jobs_completed, 1);
+        assert_eq!(snapshot.jobs_failed, 1);
+        assert!(snapshot.last_event.is_some());
+        assert!(snapshot.avg_fix_latency.is_some());
+    }
+
+    #[test]
+    fn test_coalescer_merges_events() {
+        let coalescer = Coalescer::default();
+        let path = PathBuf::from("a.SC2Replay");
+
+        coalescer.touch(path.clone(), (1, None));
+        coalescer.touch(path.clone(), (2, None));
+        coalescer.touch(PathBuf::from("b.SC2Replay"), (1, None));
+        assert_eq!(coalescer.len(), 2);
+
+        // 静默时间未到时不产生任务
+        assert!(coalescer.take_ready(Duration::from_secs(60)).is_empty());
+
+        let ready = coalescer.take_ready(Duration::ZERO);
+        assert_eq!(ready.len(), 2);
+        assert_eq!(coalescer.len(), 0);
+    }
+
+    #[test]
+    fn test_remove_dir_cancels_pending_jobs() {
+        let instance = MonitorInstance::new(FixOptions::default(), EventBus::new());
+        let removed = PathBuf::from("Replays/Multiplayer");
+        let kept = PathBuf::from("Replays/Multiplayer2");
+
+        instance.coalescer.touch(removed.join("a.SC2Replay"), (1, None));
+        instance.coalescer.touch(kept.join("b.SC2Replay"), (1, None));
+        {
+            let mut backlog = instance.backlog.lock().unwrap();
+            backlog.push_back((removed.join("c.SC2Replay"), Instant::now()));
+            backlog.push_back((kept.join("d.SC2Replay"), Instant::now()));
+        }
+
+        instance.remove_dir(&removed);
+
+        // 只取消该目录下的任务，名字相近的目录不受影响
+        let ready = instance.coalescer.take_ready(Duration::ZERO);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].0, kept.join("b.SC2Replay"));
+        let backlog: Vec<PathBuf> = instance.backlog.lock().unwrap().iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(backlog, vec![kept.join("d.SC2Replay")]);
+    }
+
+    #[test]
+    fn test_is_ignored_path() {
+        let template = crate::fixer::DEFAULT_OUTPUT_TEMPLATE;
+        assert!(!is_ignored_path(Path::new("dir/game.SC2Replay"), template));
+        assert!(is_ignored_path(Path::new("dir/game-FIXED.SC2Replay"), template));
+        assert!(is_ignored_path(Path::new("dir/game.SC2Replay.tmp"), template));
+        assert!(is_ignored_path(Path::new("dir/~game.SC2Replay"), template));
+        // 按各自的输出模板判断
+        assert!(!is_ignored_path(Path::new("dir/game-FIXED.SC2Replay"), "fixed_{stem}"));
+        assert!(is_ignored_path(Path::new("dir/fixed_game.SC2Replay"), "fixed_{stem}"));
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let bus = EventBus::new();
+        let events = bus.subscribe();
+        let instance = MonitorInstance::new(FixOptions::default(), bus);
+        assert!(!instance.is_paused());
+
+        instance.pause();
+        assert!(instance.is_paused());
+        assert!(instance.snapshot().paused);
+
+        instance.resume();
+        assert!(!instance.is_paused());
+
+        let states: Vec<Event> = events
+            .try_iter()
+            .filter(|e| matches!(e, Event::MonitorStateChanged { .. }))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                Event::MonitorStateChanged { running: true, paused: true },
+                Event::MonitorStateChanged { running: true, paused: false },
+            ]
+        );
+    }
+}
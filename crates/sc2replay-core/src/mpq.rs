@@ -0,0 +1,836 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use anyhow::Context;
+use md5::{Digest, Md5};
+
+/*
+    SC2录像是带用户数据头的MPQ归档：
+    0x00  'MPQ\x1B' 用户数据头，内容为版本化编码的录像头（版本号等）
+    N     'MPQ\x1A' 归档头，N由用户数据头中的偏移给出
+
+    修复时可以改变录像头长度或替换归档中的文件，见replace_user_data和MpqArchive::replace_file，
+    两者都会重新计算依赖的偏移：
+    - 录像头变长超出预留空间时，归档整体后移（保持512字节对齐），更新用户数据头
+    - 替换文件时按块表顺序重新排列文件数据，写入新的哈希表和块表，更新归档头中的大小、表偏移和v4的MD5，
+      以及(attributes)中该文件的CRC32和MD5
+*/
+
+const USER_DATA_MAGIC: &[u8; 4] = b"MPQ\x1B";
+const ARCHIVE_MAGIC: &[u8; 4] = b"MPQ\x1A";
+
+/// 用户数据头固定部分的长度
+pub const USER_DATA_HEADER_SIZE: usize = 16;
+
+const FLAG_EXISTS: u32 = 0x8000_0000;
+const FLAG_SINGLE_UNIT: u32 = 0x0100_0000;
+const FLAG_SECTOR_CRC: u32 = 0x0400_0000;
+const FLAG_ENCRYPTED: u32 = 0x0001_0000;
+const FLAG_COMPRESS: u32 = 0x0000_0200;
+const FLAG_IMPLODE: u32 = 0x0000_0100;
+const FLAG_FIX_KEY: u32 = 0x0002_0000;
+
+// 归档头在文件中的对齐
+const ARCHIVE_ALIGN: usize = 0x200;
+// v4归档头长度，其中0xC0起为归档头本身的MD5
+const HEADER_SIZE_V4: usize = 0xD0;
+
+// (attributes)记录每个块的校验，按块表顺序排列：版本、标志，然后是标志对应的各个数组
+const ATTRIBUTES_FILE: &str = "(attributes)";
+const ATTRIBUTES_HEADER_SIZE: usize = 8;
+const ATTR_CRC32: u32 = 0x1;
+const ATTR_FILETIME: u32 = 0x2;
+const ATTR_MD5: u32 = 0x4;
+const ATTR_PATCH_BIT: u32 = 0x8;
+
+const HASH_ENTRY_EMPTY: u32 = 0xFFFF_FFFF;
+const HASH_ENTRY_DELETED: u32 = 0xFFFF_FFFE;
+
+/// 用户数据头
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserDataHeader {
+    pub user_data_size: u32,   // 为用户数据预留的大小
+    pub archive_offset: u32,   // 归档头相对文件开头的偏移
+    pub content_size: u32,     // 实际用户数据（录像头）长度
+}
+
+impl UserDataHeader {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < USER_DATA_HEADER_SIZE || &data[0..4] != USER_DATA_MAGIC {
+            return Err(anyhow::anyhow!("不是有效的SC2Replay文件（缺少MPQ用户数据头）"));
+        }
+        Ok(Self {
+            user_data_size: read_u32(data, 4),
+            archive_offset: read_u32(data, 8),
+            content_size: read_u32(data, 12),
+        })
+    }
+
+    /// 用户数据内容在文件中的范围
+    pub fn content_range(&self) -> std::ops::Range<usize> {
+        USER_DATA_HEADER_SIZE..USER_DATA_HEADER_SIZE + self.content_size as usize
+    }
+}
+
+/// 归档头（只解析v1字段，SC2录像不需要高位偏移）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveHeader {
+    pub header_size: u32,
+    pub archive_size: u32,
+    pub format_version: u16,
+    pub sector_size_shift: u16,
+    pub hash_table_offset: u32,
+    pub block_table_offset: u32,
+    pub hash_table_entries: u32,
+    pub block_table_entries: u32,
+}
+
+impl ArchiveHeader {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < 32 || &data[0..4] != ARCHIVE_MAGIC {
+            return Err(anyhow::anyhow!("MPQ归档头无效"));
+        }
+        Ok(Self {
+            header_size: read_u32(data, 4),
+            archive_size: read_u32(data, 8),
+            format_version: read_u16(data, 12),
+            sector_size_shift: read_u16(data, 14),
+            hash_table_offset: read_u32(data, 16),
+            block_table_offset: read_u32(data, 20),
+            hash_table_entries: read_u32(data, 24),
+            block_table_entries: read_u32(data, 28),
+        })
+    }
+
+    /// 扇区大小为512 << sector_size_shift，损坏的归档头中移位过大时返回错误
+    pub fn sector_size(&self) -> anyhow::Result<usize> {
+        1usize
+            .checked_shl(u32::from(self.sector_size_shift) + 9)
+            .with_context(|| format!("扇区大小无效: {}", self.sector_size_shift))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HashEntry {
+    name_a: u32,
+    name_b: u32,
+    block_index: u32,
+}
+
+/// (attributes)中一个文件的校验，未记录的项为None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileAttributes {
+    pub crc32: Option<u32>,
+    pub md5: Option<[u8; 16]>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BlockEntry {
+    pub offset: u32,
+    pub archived_size: u32,
+    pub file_size: u32,
+    pub flags: u32,
+}
+
+/// 内存中的SC2Replay归档
+pub struct MpqArchive {
+    data: Vec<u8>,
+    pub user_data: UserDataHeader,
+    pub header: ArchiveHeader,
+    hash_table: Vec<HashEntry>,
+    block_table: Vec<BlockEntry>,
+}
+
+impl MpqArchive {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut data = Vec::new();
+        File::open(path)
+            .with_context(|| format!("无法打开文件: {}", path.display()))?
+            .read_to_end(&mut data)?;
+        Self::from_bytes(data)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> anyhow::Result<Self> {
+        let user_data = UserDataHeader::parse(&data)?;
+        let archive_offset = user_data.archive_offset as usize;
+        let header = ArchiveHeader::parse(data.get(archive_offset..).unwrap_or(&[]))?;
+
+        let hash_raw = read_table(
+            &data,
+            archive_offset + header.hash_table_offset as usize,
+            header.hash_table_entries as usize,
+            hash_string("(hash table)", HASH_FILE_KEY),
+        )?;
+        let hash_table = hash_raw
+            .chunks_exact(4)
+            .map(|e| HashEntry {
+                name_a: e[0],
+                name_b: e[1],
+                block_index: e[3],
+            })
+            .collect();
+
+        let block_raw = read_table(
+            &data,
+            archive_offset + header.block_table_offset as usize,
+            header.block_table_entries as usize,
+            hash_string("(block table)", HASH_FILE_KEY),
+        )?;
+        let block_table = block_raw
+            .chunks_exact(4)
+            .map(|e| BlockEntry {
+                offset: e[0],
+                archived_size: e[1],
+                file_size: e[2],
+                flags: e[3],
+            })
+            .collect();
+
+        Ok(Self {
+            data,
+            user_data,
+            header,
+            hash_table,
+            block_table,
+        })
+    }
+
+    /// 用户数据（录像头）内容
+    pub fn user_data_content(&self) -> &[u8] {
+        let range = self.user_data.content_range();
+        self.data.get(range).unwrap_or(&[])
+    }
+
+    pub fn block_entry(&self, name: &str) -> Option<BlockEntry> {
+        self.block_index(name).and_then(|index| self.block_table.get(index).copied())
+    }
+
+    fn block_index(&self, name: &str) -> Option<usize> {
+        let count = self.hash_table.len();
+        if count == 0 {
+            return None;
+        }
+
+        let start = hash_string(name, HASH_TABLE_OFFSET) as usize % count;
+        let name_a = hash_string(name, HASH_NAME_A);
+        let name_b = hash_string(name, HASH_NAME_B);
+
+        for i in 0..count {
+            let entry = self.hash_table[(start + i) % count];
+            if entry.block_index == HASH_ENTRY_EMPTY {
+                return None;
+            }
+            if entry.block_index != HASH_ENTRY_DELETED && entry.name_a == name_a && entry.name_b == name_b {
+                return Some(entry.block_index as usize);
+            }
+        }
+        None
+    }
+
+    /// 读取并解压归档中的文件，如"replay.details"
+    pub fn read_file(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let block = self
+            .block_entry(name)
+            .with_context(|| format!("录像中不存在文件: {}", name))?;
+
+        if block.flags & FLAG_EXISTS == 0 {
+            return Err(anyhow::anyhow!("录像中的文件已删除: {}", name));
+        }
+        if block.flags & (FLAG_ENCRYPTED | FLAG_IMPLODE) != 0 {
+            return Err(anyhow::anyhow!("不支持加密或implode压缩的文件: {}", name));
+        }
+
+        let start = self.user_data.archive_offset as usize + block.offset as usize;
+        let raw = self
+            .data
+            .get(start..start + block.archived_size as usize)
+            .with_context(|| format!("文件数据越界: {}", name))?;
+        let file_size = block.file_size as usize;
+        let compressed = block.flags & FLAG_COMPRESS != 0;
+        // 文件大小来自块表，损坏时可能很大，预分配不超过归档本身
+        let capacity = file_size.min(self.data.len());
+
+        if block.flags & FLAG_SINGLE_UNIT != 0 {
+            return if compressed && raw.len() < file_size {
+                decompress(raw, capacity)
+            } else {
+                Ok(raw.to_vec())
+            };
+        }
+
+        let sector_size = self.header.sector_size()?;
+        let sectors = file_size.div_ceil(sector_size);
+        let mut output = Vec::with_capacity(capacity);
+
+        if !compressed {
+            output.extend_from_slice(&raw[..file_size.min(raw.len())]);
+            return Ok(output);
+        }
+
+        // 压缩文件开头为扇区偏移表
+        let table_len = sectors + 1 + usize::from(block.flags & FLAG_SECTOR_CRC != 0);
+        if raw.len() < table_len * 4 {
+            return Err(anyhow::anyhow!("扇区偏移表无效: {}", name));
+        }
+        let offsets: Vec<usize> = (0..=sectors).map(|i| read_u32(raw, i * 4) as usize).collect();
+
+        for i in 0..sectors {
+            let chunk = raw
+                .get(offsets[i]..offsets[i + 1])
+                .with_context(|| format!("扇区数据越界: {}", name))?;
+            let expected = sector_size.min(file_size - i * sector_size);
+            if chunk.len() < expected {
+                output.extend(decompress(chunk, expected.min(capacity))?);
+            } else {
+                output.extend_from_slice(chunk);
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// 整个文件的内容
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// (attributes)中记录的文件校验，归档中没有(attributes)时为None
+    pub fn file_attributes(&self, name: &str) -> anyhow::Result<Option<FileAttributes>> {
+        let index = self
+            .block_index(name)
+            .with_context(|| format!("录像中不存在文件: {}", name))?;
+        if self.block_index(ATTRIBUTES_FILE).is_none() {
+            return Ok(None);
+        }
+        let attributes = self.read_file(ATTRIBUTES_FILE)?;
+        let layout = AttributesLayout::parse(&attributes, self.block_table.len())?;
+        Ok(Some(layout.get(&attributes, index)))
+    }
+
+    /// 替换归档中的文件并重建归档，返回新的文件内容
+    ///
+    /// 新文件作为单个单元写入，能压缩时用zlib压缩。归档中有(attributes)时同时更新该文件的CRC32和MD5。
+    pub fn replace_file(self, name: &str, content: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let index = self
+            .block_index(name)
+            .with_context(|| format!("录像中不存在文件: {}", name))?;
+        let mut replacements = vec![(index, content.to_vec())];
+        if let Some(attributes_index) = self.block_index(ATTRIBUTES_FILE)
+            && attributes_index != index
+        {
+            let mut attributes = self.read_file(ATTRIBUTES_FILE)?;
+            AttributesLayout::parse(&attributes, self.block_table.len())?.set(&mut attributes, index, content);
+            replacements.push((attributes_index, attributes));
+        }
+        self.rebuild(&replacements)
+    }
+
+    // 按块表顺序重新排列文件数据并写入新的表，replacements为(块索引, 新内容)
+    fn rebuild(self, replacements: &[(usize, Vec<u8>)]) -> anyhow::Result<Vec<u8>> {
+        let archive_offset = self.user_data.archive_offset as usize;
+        let archive = &self.data[archive_offset..];
+        let header_size = self.header.header_size as usize;
+        let raw_header = archive.get(..header_size.max(32)).context("MPQ归档头越界")?;
+
+        // 扩展表和按偏移加密的文件在数据移动后无法保持有效
+        let extended = |offset: usize| header_size >= offset + 8 && read_u64(raw_header, offset) != 0;
+        if extended(0x20) || extended(0x34) || extended(0x3C) {
+            return Err(anyhow::anyhow!("不支持包含扩展块表或HET/BET表的归档"));
+        }
+        if header_size >= 0x70 && read_u32(raw_header, 0x6C) != 0 {
+            return Err(anyhow::anyhow!("不支持分块校验的归档"));
+        }
+        if self.block_table.iter().any(|b| b.flags & FLAG_FIX_KEY != 0) {
+            return Err(anyhow::anyhow!("不支持按偏移加密的文件"));
+        }
+
+        // 按块表顺序重新排列文件数据
+        let mut packed = Vec::new();
+        for (index, content) in replacements {
+            let (bytes, flags) = pack_file(content)?;
+            packed.push((*index, bytes, flags, content.len()));
+        }
+        let mut output = raw_header.to_vec();
+        let mut blocks = self.block_table.clone();
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let bytes = if let Some((_, bytes, flags, file_size)) = packed.iter().find(|p| p.0 == i) {
+                block.archived_size = bytes.len() as u32;
+                block.file_size = *file_size as u32;
+                block.flags = *flags;
+                &bytes[..]
+            } else {
+                let start = block.offset as usize;
+                archive
+                    .get(start..start + block.archived_size as usize)
+                    .context("文件数据越界")?
+            };
+            block.offset = output.len() as u32;
+            output.extend_from_slice(bytes);
+        }
+
+        // 哈希表不变，原样复制；块表重新加密
+        let hash_offset = output.len();
+        let hash_start = self.header.hash_table_offset as usize;
+        let hash_len = self.hash_table.len() * 16;
+        let hash_bytes = archive.get(hash_start..hash_start + hash_len).context("MPQ表数据越界")?;
+        output.extend_from_slice(hash_bytes);
+
+        let block_offset = output.len();
+        let mut block_table: Vec<u32> = blocks
+            .iter()
+            .flat_map(|b| [b.offset, b.archived_size, b.file_size, b.flags])
+            .collect();
+        encrypt(&mut block_table, hash_string("(block table)", HASH_FILE_KEY));
+        for v in &block_table {
+            output.extend_from_slice(&v.to_le_bytes());
+        }
+
+        // 更新归档头
+        let archive_size = u32::try_from(output.len()).context("归档过大")?;
+        output[8..12].copy_from_slice(&archive_size.to_le_bytes());
+        output[16..20].copy_from_slice(&(hash_offset as u32).to_le_bytes());
+        output[20..24].copy_from_slice(&(block_offset as u32).to_le_bytes());
+        if header_size >= 0x2C {
+            output[0x28..0x2C].fill(0);
+        }
+        if header_size >= 0x34 {
+            output[0x2C..0x34].copy_from_slice(&(archive_size as u64).to_le_bytes());
+        }
+        if header_size >= HEADER_SIZE_V4 && self.header.format_version >= 3 {
+            let block_len = blocks.len() * 16;
+            output[0x44..0x4C].copy_from_slice(&(hash_len as u64).to_le_bytes());
+            output[0x4C..0x54].copy_from_slice(&(block_len as u64).to_le_bytes());
+            let block_md5 = md5(&output[block_offset..block_offset + block_len]);
+            output[0x70..0x80].copy_from_slice(&block_md5);
+            output[0x80..0x90].copy_from_slice(&md5(hash_bytes));
+            let header_md5 = md5(&output[..0xC0]);
+            output[0xC0..0xD0].copy_from_slice(&header_md5);
+        }
+
+        let mut data = self.data;
+        data.truncate(archive_offset);
+        data.extend(output);
+        Ok(data)
+    }
+}
+
+// (attributes)中各数组的位置
+struct AttributesLayout {
+    entries: usize,
+    crc32: Option<usize>,
+    md5: Option<usize>,
+}
+
+impl AttributesLayout {
+    // 按标志和文件长度确定数组位置；有的归档不为(attributes)自身记录校验，数组比块表少一项
+    fn parse(data: &[u8], block_count: usize) -> anyhow::Result<Self> {
+        if data.len() < ATTRIBUTES_HEADER_SIZE {
+            return Err(anyhow::anyhow!("(attributes)长度无效"));
+        }
+        let flags = read_u32(data, 4);
+        let size_of = |entries: usize| {
+            let mut size = ATTRIBUTES_HEADER_SIZE;
+            for (flag, item) in [(ATTR_CRC32, 4), (ATTR_FILETIME, 8), (ATTR_MD5, 16)] {
+                if flags & flag != 0 {
+                    size += entries * item;
+                }
+            }
+            if flags & ATTR_PATCH_BIT != 0 {
+                size += entries.div_ceil(8);
+            }
+            size
+        };
+        let entries = [block_count, block_count.saturating_sub(1)]
+            .into_iter()
+            .find(|&n| size_of(n) == data.len())
+            .context("(attributes)长度与块表不符")?;
+
+        let crc32 = (flags & ATTR_CRC32 != 0).then_some(ATTRIBUTES_HEADER_SIZE);
+        let mut md5 = ATTRIBUTES_HEADER_SIZE;
+        if flags & ATTR_CRC32 != 0 {
+            md5 += entries * 4;
+        }
+        if flags & ATTR_FILETIME != 0 {
+            md5 += entries * 8;
+        }
+        let md5 = (flags & ATTR_MD5 != 0).then_some(md5);
+        Ok(Self { entries, crc32, md5 })
+    }
+
+    fn get(&self, data: &[u8], index: usize) -> FileAttributes {
+        if index >= self.entries {
+            return FileAttributes { crc32: None, md5: None };
+        }
+        FileAttributes {
+            crc32: self.crc32.map(|offset| read_u32(data, offset + index * 4)),
+            md5: self.md5.map(|offset| {
+                let start = offset + index * 16;
+                data[start..start + 16].try_into().unwrap_or_default()
+            }),
+        }
+    }
+
+    // 写入块index的新内容的校验
+    fn set(&self, data: &mut [u8], index: usize, content: &[u8]) {
+        if index >= self.entries {
+            return;
+        }
+        if let Some(offset) = self.crc32 {
+            let start = offset + index * 4;
+            data[start..start + 4].copy_from_slice(&crc32fast::hash(content).to_le_bytes());
+        }
+        if let Some(offset) = self.md5 {
+            let start = offset + index * 16;
+            data[start..start + 16].copy_from_slice(&md5(content));
+        }
+    }
+}
+
+/// 用新的内容替换用户数据（录像头），返回新的文件内容
+///
+/// 只需要data包含到归档头为止的部分，其后的字节原样保留，因此也可用于只读入开头的流式修复。
+/// 内容超出预留空间时用户数据头的预留大小和归档偏移一起增加，归档内的偏移都相对归档头，不需要改动。
+pub fn replace_user_data(mut data: Vec<u8>, content: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let user_data = UserDataHeader::parse(&data)?;
+    let reserved = user_data.user_data_size as usize;
+    let archive_offset = user_data.archive_offset as usize;
+    let range = user_data.content_range();
+    if range.end > archive_offset || archive_offset > data.len() {
+        return Err(anyhow::anyhow!("MPQ用户数据头中的偏移无效"));
+    }
+
+    let needed = USER_DATA_HEADER_SIZE + content.len();
+    let grow = needed
+        .saturating_sub(archive_offset)
+        .max(content.len().saturating_sub(reserved))
+        .next_multiple_of(ARCHIVE_ALIGN);
+    let reserved = u32::try_from(reserved + grow).context("录像头过长")?;
+    let new_offset = u32::try_from(archive_offset + grow).context("录像头过长")?;
+
+    // 保留录像头之后、归档之前的原有字节，空间变化时截断或补零
+    let mut output = data[..archive_offset].to_vec();
+    output.splice(range, content.iter().copied());
+    output.resize(new_offset as usize, 0);
+    output[4..8].copy_from_slice(&reserved.to_le_bytes());
+    output[8..12].copy_from_slice(&new_offset.to_le_bytes());
+    output[12..16].copy_from_slice(&(content.len() as u32).to_le_bytes());
+    output.extend(data.drain(archive_offset..));
+    Ok(output)
+}
+
+// 把文件打包为单个单元，压缩后更小时使用zlib压缩
+fn pack_file(content: &[u8]) -> anyhow::Result<(Vec<u8>, u32)> {
+    let mut encoder = flate2::write::ZlibEncoder::new(vec![0x02], flate2::Compression::best());
+    encoder.write_all(content)?;
+    let compressed = encoder.finish()?;
+    if compressed.len() < content.len() {
+        Ok((compressed, FLAG_EXISTS | FLAG_SINGLE_UNIT | FLAG_COMPRESS))
+    } else {
+        Ok((content.to_vec(), FLAG_EXISTS | FLAG_SINGLE_UNIT))
+    }
+}
+
+// 按压缩类型字节解压一个扇区，capacity为预分配大小，实际数据更多时随解压增长
+fn decompress(chunk: &[u8], capacity: usize) -> anyhow::Result<Vec<u8>> {
+    let (&mask, payload) = chunk.split_first().context("压缩数据为空")?;
+    let mut output = Vec::with_capacity(capacity);
+
+    match mask {
+        0x10 => {
+            bzip2::read::BzDecoder::new(payload).read_to_end(&mut output)?;
+        }
+        0x02 => {
+            flate2::read::ZlibDecoder::new(payload).read_to_end(&mut output)?;
+        }
+        other => return Err(anyhow::anyhow!("不支持的压缩类型: 0x{:02X}", other)),
+    }
+
+    Ok(output)
+}
+
+fn read_table(data: &[u8], offset: usize, entries: usize, key: u32) -> anyhow::Result<Vec<u32>> {
+    let bytes = data
+        .get(offset..offset + entries * 16)
+        .context("MPQ表数据越界")?;
+    let mut table: Vec<u32> = bytes.chunks_exact(4).map(|b| read_u32(b, 0)).collect();
+    decrypt(&mut table, key);
+    Ok(table)
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from(read_u32(data, offset)) | u64::from(read_u32(data, offset + 4)) << 32
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+// MPQ哈希类型
+const HASH_TABLE_OFFSET: u32 = 0;
+const HASH_NAME_A: u32 = 1;
+const HASH_NAME_B: u32 = 2;
+const HASH_FILE_KEY: u32 = 3;
+
+lazy_static::lazy_static! {
+    static ref CRYPT_TABLE: [u32; 0x500] = build_crypt_table();
+}
+
+fn build_crypt_table() -> [u32; 0x500] {
+    let mut table = [0u32; 0x500];
+    let mut seed: u32 = 0x0010_0001;
+
+    for index1 in 0..0x100 {
+        let mut index2 = index1;
+        for _ in 0..5 {
+            seed = (seed * 125 + 3) % 0x2AAAAB;
+            let high = (seed & 0xFFFF) << 0x10;
+            seed = (seed * 125 + 3) % 0x2AAAAB;
+            let low = seed & 0xFFFF;
+            table[index2] = high | low;
+            index2 += 0x100;
+        }
+    }
+    table
+}
+
+pub fn hash_string(name: &str, hash_type: u32) -> u32 {
+    let mut seed1: u32 = 0x7FED_7FED;
+    let mut seed2: u32 = 0xEEEE_EEEE;
+
+    for ch in name.bytes() {
+        let ch = match ch.to_ascii_uppercase() {
+            b'/' => b'\\',
+            c => c,
+        } as u32;
+        seed1 = CRYPT_TABLE[(hash_type * 0x100 + ch) as usize] ^ seed1.wrapping_add(seed2);
+        seed2 = ch
+            .wrapping_add(seed1)
+            .wrapping_add(seed2)
+            .wrapping_add(seed2 << 5)
+            .wrapping_add(3);
+    }
+    seed1
+}
+
+fn encrypt(data: &mut [u32], mut key: u32) {
+    let mut seed: u32 = 0xEEEE_EEEE;
+
+    for value in data.iter_mut() {
+        seed = seed.wrapping_add(CRYPT_TABLE[0x400 + (key & 0xFF) as usize]);
+        let plain = *value;
+        *value = plain ^ key.wrapping_add(seed);
+        key = ((!key) << 0x15).wrapping_add(0x1111_1111) | (key >> 0x0B);
+        seed = plain
+            .wrapping_add(seed)
+            .wrapping_add(seed << 5)
+            .wrapping_add(3);
+    }
+}
+
+// v4归档头中各表和归档头本身的校验
+fn md5(data: &[u8]) -> [u8; 16] {
+    Md5::digest(data).into()
+}
+
+fn decrypt(data: &mut [u32], mut key: u32) {
+    let mut seed: u32 = 0xEEEE_EEEE;
+
+    for value in data.iter_mut() {
+        seed = seed.wrapping_add(CRYPT_TABLE[0x400 + (key & 0xFF) as usize]);
+        let plain = *value ^ key.wrapping_add(seed);
+        key = ((!key) << 0x15).wrapping_add(0x1111_1111) | (key >> 0x0B);
+        seed = plain
+            .wrapping_add(seed)
+            .wrapping_add(seed << 5)
+            .wrapping_add(3);
+        *value = plain;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_string_known_keys() {
+        // MPQ格式中固定的表密钥
+        assert_eq!(hash_string("(hash table)", HASH_FILE_KEY), 0xC3AF3770);
+        assert_eq!(hash_string("(block table)", HASH_FILE_KEY), 0xEC83B3A3);
+    }
+
+    #[test]
+    fn test_user_data_header() {
+        let mut data = b"MPQ\x1B".to_vec();
+        data.extend_from_slice(&0x200u32.to_le_bytes());
+        data.extend_from_slice(&0x400u32.to_le_bytes());
+        data.extend_from_slice(&0x6Au32.to_le_bytes());
+
+        let header = UserDataHeader::parse(&data).unwrap();
+        assert_eq!(header.archive_offset, 0x400);
+        assert_eq!(header.content_range(), 16..16 + 0x6A);
+        assert!(UserDataHeader::parse(b"MPQ\x1A").is_err());
+    }
+
+    // 构造只包含单个未压缩文件的最小归档
+    pub(crate) fn build_archive(user_data: &[u8], name: &str, content: &[u8]) -> Vec<u8> {
+        build_archive_files(user_data, &[(name, content)])
+    }
+
+    // 构造包含多个未压缩文件的最小归档，文件按顺序存放
+    pub(crate) fn build_archive_files(user_data: &[u8], files: &[(&str, &[u8])]) -> Vec<u8> {
+        let archive_offset = 0x400usize;
+        let mut data = b"MPQ\x1B".to_vec();
+        data.extend_from_slice(&0x200u32.to_le_bytes());
+        data.extend_from_slice(&(archive_offset as u32).to_le_bytes());
+        data.extend_from_slice(&(user_data.len() as u32).to_le_bytes());
+        data.extend_from_slice(user_data);
+        data.resize(archive_offset, 0);
+
+        let hash_entries = 4u32;
+        let file_offset = 32u32;
+        let files_size: u32 = files.iter().map(|(_, c)| c.len() as u32).sum();
+        let hash_offset = file_offset + files_size;
+        let block_offset = hash_offset + hash_entries * 16;
+
+        data.extend_from_slice(b"MPQ\x1A");
+        for v in [32u32, block_offset + files.len() as u32 * 16] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&3u16.to_le_bytes());
+        for v in [hash_offset, block_offset, hash_entries, files.len() as u32] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+
+        let mut hash_table = vec![HASH_ENTRY_EMPTY; hash_entries as usize * 4];
+        let mut block_table = Vec::new();
+        let mut offset = file_offset;
+        for (index, (name, content)) in files.iter().enumerate() {
+            data.extend_from_slice(content);
+
+            // 冲突时顺延到下一个空位
+            let mut slot = hash_string(name, HASH_TABLE_OFFSET) % hash_entries;
+            while hash_table[slot as usize * 4 + 3] != HASH_ENTRY_EMPTY {
+                slot = (slot + 1) % hash_entries;
+            }
+            let slot = slot as usize * 4;
+            hash_table[slot] = hash_string(name, HASH_NAME_A);
+            hash_table[slot + 1] = hash_string(name, HASH_NAME_B);
+            hash_table[slot + 2] = 0;
+            hash_table[slot + 3] = index as u32;
+
+            block_table.extend_from_slice(&[offset, content.len() as u32, content.len() as u32, FLAG_EXISTS | FLAG_SINGLE_UNIT]);
+            offset += content.len() as u32;
+        }
+        encrypt(&mut hash_table, hash_string("(hash table)", HASH_FILE_KEY));
+        encrypt(&mut block_table, hash_string("(block table)", HASH_FILE_KEY));
+
+        for v in hash_table.iter().chain(block_table.iter()) {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data
+    }
+
+    // 记录CRC32、FILETIME和MD5的(attributes)，不包括自身
+    pub(crate) fn build_attributes(files: &[&[u8]]) -> Vec<u8> {
+        let mut data = 100u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&(ATTR_CRC32 | ATTR_FILETIME | ATTR_MD5).to_le_bytes());
+        for content in files {
+            data.extend_from_slice(&crc32fast::hash(content).to_le_bytes());
+        }
+        data.resize(data.len() + files.len() * 8, 0);
+        for content in files {
+            data.extend_from_slice(&md5(content));
+        }
+        data
+    }
+
+    #[test]
+    fn test_sector_size() {
+        let mut data = ARCHIVE_MAGIC.to_vec();
+        data.resize(32, 0);
+        data[14] = 3;
+        assert_eq!(ArchiveHeader::parse(&data).unwrap().sector_size().unwrap(), 4096);
+
+        // 损坏的归档头：移位超出usize位数时不会溢出
+        data[14] = 55;
+        assert!(ArchiveHeader::parse(&data).unwrap().sector_size().is_err());
+        data[14..16].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(ArchiveHeader::parse(&data).unwrap().sector_size().is_err());
+    }
+
+    #[test]
+    fn test_read_file_from_archive() {
+        let data = build_archive(b"header", "replay.details", b"details-content");
+        let archive = MpqArchive::from_bytes(data).unwrap();
+
+        assert_eq!(archive.user_data_content(), b"header");
+        assert_eq!(archive.header.hash_table_entries, 4);
+        assert_eq!(archive.read_file("replay.details").unwrap(), b"details-content");
+        assert!(archive.read_file("replay.initData").is_err());
+    }
+
+    #[test]
+    fn test_replace_user_data() {
+        let data = build_archive(b"header", "replay.details", b"details-content");
+
+        // 预留空间内变长，归档不动
+        let data = replace_user_data(data, b"longer-header").unwrap();
+        let archive = MpqArchive::from_bytes(data).unwrap();
+        assert_eq!(archive.user_data.archive_offset, 0x400);
+        assert_eq!(archive.user_data_content(), b"longer-header");
+
+        // 超出预留空间，归档按512字节后移
+        let long = vec![0x5A; 0x300];
+        let data = replace_user_data(archive.into_bytes(), &long).unwrap();
+        let archive = MpqArchive::from_bytes(data).unwrap();
+        assert_eq!(archive.user_data.archive_offset, 0x600);
+        assert_eq!(archive.user_data.user_data_size, 0x400);
+        assert_eq!(archive.user_data_content(), &long[..]);
+        assert_eq!(archive.read_file("replay.details").unwrap(), b"details-content");
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let data = build_archive(b"header", "replay.details", b"details-content");
+        let content = b"new-details".repeat(100);
+        let data = MpqArchive::from_bytes(data).unwrap().replace_file("replay.details", &content).unwrap();
+
+        let archive = MpqArchive::from_bytes(data).unwrap();
+        let block = archive.block_entry("replay.details").unwrap();
+        // 重复内容压缩后更小
+        assert_ne!(block.flags & FLAG_COMPRESS, 0);
+        assert_eq!(archive.read_file("replay.details").unwrap(), content);
+        assert_eq!(archive.user_data_content(), b"header");
+        assert!(archive.replace_file("replay.initData", b"").is_err());
+    }
+
+    #[test]
+    fn test_replace_file_attributes() {
+        let attributes = build_attributes(&[b"details-content", b"init-data"]);
+        let data = build_archive_files(
+            b"header",
+            &[("replay.details", b"details-content"), ("replay.initData", b"init-data"), (ATTRIBUTES_FILE, &attributes)],
+        );
+        let archive = MpqArchive::from_bytes(data).unwrap();
+        let expected = |content: &[u8]| FileAttributes { crc32: Some(crc32fast::hash(content)), md5: Some(md5(content)) };
+        assert_eq!(archive.file_attributes("replay.details").unwrap(), Some(expected(b"details-content")));
+
+        let content = b"new-details".repeat(100);
+        let archive = MpqArchive::from_bytes(archive.replace_file("replay.details", &content).unwrap()).unwrap();
+        assert_eq!(archive.read_file("replay.details").unwrap(), content);
+        assert_eq!(archive.file_attributes("replay.details").unwrap(), Some(expected(&content)));
+        // 其他文件的校验不变
+        assert_eq!(archive.read_file("replay.initData").unwrap(), b"init-data");
+        assert_eq!(archive.file_attributes("replay.initData").unwrap(), Some(expected(b"init-data")));
+        assert_eq!(archive.file_attributes(ATTRIBUTES_FILE).unwrap(), Some(FileAttributes { crc32: None, md5: None }));
+
+        // 没有(attributes)的归档
+        let data = build_archive(b"header", "replay.details", b"details-content");
+        assert_eq!(MpqArchive::from_bytes(data).unwrap().file_attributes("replay.details").unwrap(), None);
+    }
+}
@@ -0,0 +1,364 @@
+//! 修复操作
+//!
+//! 每条修复规则对应一个实现了[`Patch`]的操作，在[`ReplayEditor`]上修改录像。
+//! 操作只负责找到要改的位置，改变长度或替换归档文件后的MPQ偏移由编辑器重新计算：
+//!
+//! - [`ReplaceBytes`]：在文件开头查找并替换字节序列，长度不同时只能在录像头内
+//! - [`SetHeaderField`]：改写录像头中的整数字段，如版本号中的build，编码长度可以变化
+//! - [`ReplaceInFile`]：在归档内的文件（如replay.details）中查找并替换字节，替换后重建归档
+
+use std::fmt;
+use std::ops::Range;
+use anyhow::Context;
+
+use crate::mpq::{replace_user_data, MpqArchive, UserDataHeader};
+use crate::replay::{encode_vint, find_int_field, ReplayHeader};
+use crate::rules::format_hex;
+
+/// 一次修改的位置和内容，记入日志和修复记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchChange {
+    /// 被修改的归档文件，如"replay.details"；None表示直接修改录像文件
+    pub archive_file: Option<String>,
+    /// 在录像文件或归档文件中的偏移
+    pub offset: usize,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+}
+
+/// 操作需要读取的录像范围，决定能否只读入开头流式修复
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchScope {
+    /// 文件开头的这些字节
+    Prefix(usize),
+    /// 到归档头为止的录像头部分
+    Header,
+    /// 整个录像
+    Archive,
+}
+
+/// 修复操作
+pub trait Patch: fmt::Debug + Send + Sync {
+    /// 简短说明，如"替换字节 09 00 -> 09 0A（前128字节）"
+    fn describe(&self) -> String;
+
+    fn scope(&self) -> PatchScope;
+
+    /// 应用到录像，不适用时返回Ok(None)且不修改录像
+    fn apply(&self, replay: &mut ReplayEditor) -> anyhow::Result<Option<PatchChange>>;
+}
+
+/// 内存中的录像，修改后维护MPQ用户数据头和归档头中依赖的偏移
+///
+/// 流式修复时只包含录像开头的一部分，见[`PatchScope`]。
+#[derive(Debug, Clone)]
+pub struct ReplayEditor {
+    data: Vec<u8>,
+}
+
+impl ReplayEditor {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// 录像头（MPQ用户数据内容）在文件中的范围
+    pub fn header_range(&self) -> anyhow::Result<Range<usize>> {
+        let range = UserDataHeader::parse(&self.data)?.content_range();
+        if range.end > self.data.len() {
+            return Err(anyhow::anyhow!("录像头长度超出文件"));
+        }
+        Ok(range)
+    }
+
+    /// 录像头中的base build，录像头无法解析或没有该字段时为None
+    pub fn base_build(&self) -> Option<u32> {
+        let range = self.header_range().ok()?;
+        let header = ReplayHeader::parse(&self.data[range]).ok()?;
+        Some(header.version.base_build).filter(|b| *b != 0)
+    }
+
+    /// 替换文件中range范围的字节
+    ///
+    /// 长度不变时直接覆盖，不要求文件是有效的MPQ归档；长度改变时range必须在录像头内，
+    /// 录像头之后的归档按需要整体后移。
+    pub fn splice(&mut self, range: Range<usize>, replacement: &[u8]) -> anyhow::Result<()> {
+        if range.start > range.end || range.end > self.data.len() {
+            return Err(anyhow::anyhow!("修改范围超出文件: {:?}", range));
+        }
+        if range.len() == replacement.len() {
+            self.data[range].copy_from_slice(replacement);
+            return Ok(());
+        }
+
+        let header = self.header_range()?;
+        if range.start < header.start || range.end > header.end {
+            return Err(anyhow::anyhow!("只能在录像头内改变长度: {:?}", range));
+        }
+        let mut content = self.data[header.clone()].to_vec();
+        content.splice(range.start - header.start..range.end - header.start, replacement.iter().copied());
+        self.data = replace_user_data(std::mem::take(&mut self.data), &content)?;
+        Ok(())
+    }
+
+    /// 读取并解压归档中的文件
+    pub fn read_file(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        MpqArchive::from_bytes(self.data.clone())?.read_file(name)
+    }
+
+    /// 替换归档中的文件，重建哈希表、块表和归档头
+    pub fn replace_file(&mut self, name: &str, content: &[u8]) -> anyhow::Result<()> {
+        self.data = MpqArchive::from_bytes(self.data.clone())?.replace_file(name, content)?;
+        Ok(())
+    }
+}
+
+/// 在前limit个字节内查找字节序列偏移
+pub fn find_bytes_within(data: &[u8], search: &[u8], limit: usize) -> Option<usize> {
+    let data_len = data.len().min(limit);
+    if search.is_empty() || data_len < search.len() {
+        return None;
+    }
+
+    data[..data_len]
+        .windows(search.len())
+        .position(|window| window == search)
+}
+
+/// 在文件开头scan_limit个字节内查找字节序列并替换
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaceBytes {
+    pub search: Vec<u8>,
+    pub replace: Vec<u8>,
+    pub scan_limit: usize,
+}
+
+impl Patch for ReplaceBytes {
+    fn describe(&self) -> String {
+        format!(
+            "替换字节 {} -> {}（前{}字节）",
+            format_hex(&self.search),
+            format_hex(&self.replace),
+            self.scan_limit
+        )
+    }
+
+    fn scope(&self) -> PatchScope {
+        PatchScope::Prefix(self.scan_limit)
+    }
+
+    fn apply(&self, replay: &mut ReplayEditor) -> anyhow::Result<Option<PatchChange>> {
+        let Some(offset) = find_bytes_within(replay.data(), &self.search, self.scan_limit) else {
+            return Ok(None);
+        };
+        replay.splice(offset..offset + self.search.len(), &self.replace)?;
+        Ok(Some(PatchChange {
+            archive_file: None,
+            offset,
+            before: self.search.clone(),
+            after: self.replace.clone(),
+        }))
+    }
+}
+
+/// 改写录像头中的整数字段
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetHeaderField {
+    /// 结构体字段标签路径，如build为[1, 4]
+    pub field: Vec<i64>,
+    /// 只在当前值为此值时修改，None则不限
+    pub from: Option<i64>,
+    pub to: i64,
+}
+
+impl Patch for SetHeaderField {
+    fn describe(&self) -> String {
+        let field = self.field.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(".");
+        match self.from {
+            Some(from) => format!("录像头字段{}: {} -> {}", field, from, self.to),
+            None => format!("录像头字段{} -> {}", field, self.to),
+        }
+    }
+
+    fn scope(&self) -> PatchScope {
+        PatchScope::Header
+    }
+
+    fn apply(&self, replay: &mut ReplayEditor) -> anyhow::Result<Option<PatchChange>> {
+        let header = replay.header_range()?;
+        let Some((range, value)) = find_int_field(&replay.data()[header.clone()], &self.field)? else {
+            return Ok(None);
+        };
+        if value == self.to || self.from.is_some_and(|from| from != value) {
+            return Ok(None);
+        }
+
+        let range = header.start + range.start..header.start + range.end;
+        let before = replay.data()[range.clone()].to_vec();
+        if before.first() != Some(&0x09) {
+            return Err(anyhow::anyhow!("录像头字段不是变长整数，无法改写"));
+        }
+        let mut after = vec![0x09];
+        after.extend(encode_vint(self.to));
+        replay.splice(range.clone(), &after)?;
+
+        Ok(Some(PatchChange {
+            archive_file: None,
+            offset: range.start,
+            before,
+            after,
+        }))
+    }
+}
+
+/// 在归档内的文件中查找并替换字节序列，长度可以不同
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaceInFile {
+    /// 归档内的文件名，如"replay.details"
+    pub file: String,
+    pub search: Vec<u8>,
+    pub replace: Vec<u8>,
+}
+
+impl Patch for ReplaceInFile {
+    fn describe(&self) -> String {
+        format!("{}中替换字节 {} -> {}", self.file, format_hex(&self.search), format_hex(&self.replace))
+    }
+
+    fn scope(&self) -> PatchScope {
+        PatchScope::Archive
+    }
+
+    fn apply(&self, replay: &mut ReplayEditor) -> anyhow::Result<Option<PatchChange>> {
+        let mut content = replay.read_file(&self.file)?;
+        let Some(offset) = find_bytes_within(&content, &self.search, usize::MAX) else {
+            return Ok(None);
+        };
+        content.splice(offset..offset + self.search.len(), self.replace.iter().copied());
+        replay
+            .replace_file(&self.file, &content)
+            .with_context(|| format!("无法替换录像中的文件: {}", self.file))?;
+
+        Ok(Some(PatchChange {
+            archive_file: Some(self.file.clone()),
+            offset,
+            before: self.search.clone(),
+            after: self.replace.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md5::{Digest, Md5};
+    use crate::mpq::tests::{build_archive, build_archive_files, build_attributes};
+    use crate::mpq::FileAttributes;
+    use crate::replay::ReplayHeader;
+
+    // 结构体{1: {1: 5, 2: 0, 3: 15, 4: 95687, 5: 95687}, 3: 100}
+    fn header_content() -> Vec<u8> {
+        let build = [0x09, 0x8E, 0xD7, 0x0B];
+        let mut data = vec![0x05, 0x04, 0x02, 0x05, 0x0A];
+        data.extend_from_slice(&[0x02, 0x09, 0x0A, 0x04, 0x09, 0x00, 0x06, 0x09, 0x1E, 0x08]);
+        data.extend_from_slice(&build);
+        data.push(0x0A);
+        data.extend_from_slice(&build);
+        data.extend_from_slice(&[0x06, 0x09, 0xC8, 0x01]);
+        data
+    }
+
+    #[test]
+    fn test_replace_bytes_length_change() {
+        let data = build_archive(&header_content(), "replay.details", b"details-content");
+        let mut replay = ReplayEditor::new(data);
+
+        // 长度不同，录像头变长，归档仍可读取
+        let patch = ReplaceBytes {
+            search: vec![0x09, 0x1E],
+            replace: vec![0x09, 0x1E, 0x00, 0x00],
+            scan_limit: 128,
+        };
+        let change = patch.apply(&mut replay).unwrap().unwrap();
+        assert_eq!(change.offset, 16 + 12);
+        let archive = MpqArchive::from_bytes(replay.data().to_vec()).unwrap();
+        assert_eq!(archive.user_data.content_size as usize, header_content().len() + 2);
+        assert_eq!(archive.read_file("replay.details").unwrap(), b"details-content");
+
+        // 在归档内改变长度被拒绝
+        let patch = ReplaceBytes {
+            search: b"MPQ\x1A".to_vec(),
+            replace: b"MPQ".to_vec(),
+            scan_limit: usize::MAX,
+        };
+        assert!(patch.apply(&mut replay).is_err());
+    }
+
+    #[test]
+    fn test_set_header_field() {
+        let data = build_archive(&header_content(), "replay.details", b"details-content");
+        let mut replay = ReplayEditor::new(data);
+
+        let patch = SetHeaderField { field: vec![1, 4], from: Some(95687), to: 1_000_000_000 };
+        let change = patch.apply(&mut replay).unwrap().unwrap();
+        assert_eq!(change.before, vec![0x09, 0x8E, 0xD7, 0x0B]);
+        assert_eq!(change.after.len(), 6);
+
+        let archive = MpqArchive::from_bytes(replay.data().to_vec()).unwrap();
+        let header = ReplayHeader::parse(archive.user_data_content()).unwrap();
+        assert_eq!(header.version.build, 1_000_000_000);
+        assert_eq!(header.version.base_build, 95687);
+        assert_eq!(header.elapsed_game_loops, 100);
+        assert_eq!(archive.read_file("replay.details").unwrap(), b"details-content");
+
+        // 已是目标值或当前值不符时不修改
+        assert_eq!(patch.apply(&mut replay).unwrap(), None);
+        let patch = SetHeaderField { field: vec![1, 5], from: Some(1), to: 2 };
+        assert_eq!(patch.apply(&mut replay).unwrap(), None);
+    }
+
+    #[test]
+    fn test_replace_in_file() {
+        let attributes = build_attributes(&[b"details-content"]);
+        let data = build_archive_files(
+            &header_content(),
+            &[("replay.details", b"details-content"), ("(attributes)", &attributes)],
+        );
+        let mut replay = ReplayEditor::new(data);
+
+        let patch = ReplaceInFile {
+            file: "replay.details".to_string(),
+            search: b"content".to_vec(),
+            replace: b"rewritten-content".repeat(20),
+        };
+        let change = patch.apply(&mut replay).unwrap().unwrap();
+        assert_eq!(change.archive_file.as_deref(), Some("replay.details"));
+        assert_eq!(change.offset, 8);
+
+        let archive = MpqArchive::from_bytes(replay.data().to_vec()).unwrap();
+        let mut expected = b"details-".to_vec();
+        expected.extend(b"rewritten-content".repeat(20));
+        assert_eq!(archive.read_file("replay.details").unwrap(), expected);
+        assert_eq!(archive.user_data_content(), header_content());
+        assert_eq!(archive.header.archive_size as usize, replay.data().len() - archive.user_data.archive_offset as usize);
+        // (attributes)中的校验随文件更新
+        assert_eq!(
+            archive.file_attributes("replay.details").unwrap(),
+            Some(FileAttributes { crc32: Some(crc32fast::hash(&expected)), md5: Some(Md5::digest(&expected).into()) })
+        );
+
+        let missing = ReplaceInFile {
+            file: "replay.initData".to_string(),
+            search: vec![0],
+            replace: vec![1],
+        };
+        assert!(missing.apply(&mut replay).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use anyhow::Context;
+
+use crate::mpq::MpqArchive;
+
+/*
+    SC2录像头和replay.details使用的"版本化"编码
+    每个值以一个类型字节开头：
+    0x00 数组  0x01 位数组  0x02 数据块  0x03 选择
+    0x04 可选  0x05 结构体  0x06 u8  0x07 u32  0x08 u64  0x09 变长整数
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Array(Vec<Value>),
+    BitArray(u64, Vec<u8>),
+    Blob(Vec<u8>),
+    Choice(i64, Box<Value>),
+    Optional(Option<Box<Value>>),
+    Struct(Vec<(i64, Value)>),
+    Int(i64),
+}
+
+impl Value {
+    /// 结构体字段，自动展开可选值
+    pub fn field(&self, tag: i64) -> Option<&Value> {
+        match self.unwrap_optional()? {
+            Value::Struct(fields) => fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self.unwrap_optional()? {
+            Value::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_blob(&self) -> Option<&[u8]> {
+        match self.unwrap_optional()? {
+            Value::Blob(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self.unwrap_optional()? {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn unwrap_optional(&self) -> Option<&Value> {
+        match self {
+            Value::Optional(inner) => inner.as_deref().and_then(|v| v.unwrap_optional()),
+            other => Some(other),
+        }
+    }
+}
+
+pub struct VersionedDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> VersionedDecoder<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// 当前读取位置
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn read_byte(&mut self) -> anyhow::Result<u8> {
+        let b = *self.data.get(self.pos).context("版本化数据意外结束")?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .context("版本化数据意外结束")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_vint(&mut self) -> anyhow::Result<i64> {
+        let mut b = self.read_byte()?;
+        let negative = b & 1 != 0;
+        let mut result = ((b >> 1) & 0x3F) as i64;
+        let mut bits = 6;
+
+        while b & 0x80 != 0 {
+            b = self.read_byte()?;
+            if bits >= 63 {
+                return Err(anyhow::anyhow!("变长整数过长"));
+            }
+            result |= ((b & 0x7F) as i64) << bits;
+            bits += 7;
+        }
+
+        Ok(if negative { -result } else { result })
+    }
+
+    fn read_len(&mut self) -> anyhow::Result<usize> {
+        let len = self.read_vint()?;
+        usize::try_from(len).map_err(|_| anyhow::anyhow!("长度无效: {}", len))
+    }
+
+    // 在当前值中按结构体字段标签路径查找整数，只展开结构体和可选值
+    fn find_int_field(&mut self, path: &[i64]) -> anyhow::Result<Option<(Range<usize>, i64)>> {
+        let Some((&tag, rest)) = path.split_first() else {
+            let start = self.pos;
+            return Ok(match self.read_value()? {
+                Value::Int(v) => Some((start..self.pos, v)),
+                _ => None,
+            });
+        };
+
+        match self.read_byte()? {
+            0x04 if self.read_byte()? != 0 => self.find_int_field(path),
+            0x05 => {
+                let count = self.read_len()?;
+                for _ in 0..count {
+                    if self.read_vint()? == tag {
+                        return self.find_int_field(rest);
+                    }
+                    self.read_value()?;
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn read_value(&mut self) -> anyhow::Result<Value> {
+        let value = match self.read_byte()? {
+            0x00 => {
+                let len = self.read_len()?;
+                let mut items = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    items.push(self.read_value()?);
+                }
+                Value::Array(items)
+            }
+            0x01 => {
+                let bits = self.read_len()?;
+                Value::BitArray(bits as u64, self.read_bytes(bits.div_ceil(8))?.to_vec())
+            }
+            0x02 => {
+                let len = self.read_len()?;
+                Value::Blob(self.read_bytes(len)?.to_vec())
+            }
+            0x03 => {
+                let tag = self.read_vint()?;
+                Value::Choice(tag, Box::new(self.read_value()?))
+            }
+            0x04 => {
+                if self.read_byte()? != 0 {
+                    Value::Optional(Some(Box::new(self.read_value()?)))
+                } else {
+                    Value::Optional(None)
+                }
+            }
+            0x05 => {
+                let count = self.read_len()?;
+                let mut fields = Vec::with_capacity(count.min(1024));
+                for _ in 0..count {
+                    let tag = self.read_vint()?;
+                    fields.push((tag, self.read_value()?));
+                }
+                Value::Struct(fields)
+            }
+            0x06 => Value::Int(self.read_byte()? as i64),
+            0x07 => Value::Int(u32::from_le_bytes(self.read_bytes(4)?.try_into()?) as i64),
+            0x08 => Value::Int(u64::from_le_bytes(self.read_bytes(8)?.try_into()?) as i64),
+            0x09 => Value::Int(self.read_vint()?),
+            other => return Err(anyhow::anyhow!("未知的版本化类型: 0x{:02X}", other)),
+        };
+        Ok(value)
+    }
+}
+
+/// 按结构体字段标签路径查找整数字段，返回其编码（含类型字节）在data中的范围和当前值
+///
+/// 如录像头中的build为`[1, 4]`，base build为`[1, 5]`
+pub fn find_int_field(data: &[u8], path: &[i64]) -> anyhow::Result<Option<(Range<usize>, i64)>> {
+    VersionedDecoder::new(data).find_int_field(path)
+}
+
+/// 按版本化编码写出变长整数（不含类型字节0x09）
+pub fn encode_vint(value: i64) -> Vec<u8> {
+    let mut rest = value.unsigned_abs();
+    let mut byte = ((rest & 0x3F) << 1) as u8 | u8::from(value < 0);
+    rest >>= 6;
+
+    let mut bytes = Vec::new();
+    while rest != 0 {
+        bytes.push(byte | 0x80);
+        byte = (rest & 0x7F) as u8;
+        rest >>= 7;
+    }
+    bytes.push(byte);
+    bytes
+}
+
+/// 录像的客户端版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReplayVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub revision: u32,
+    pub build: u32,
+    pub base_build: u32,
+}
+
+impl fmt::Display for ReplayVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.major, self.minor, self.revision, self.build)
+    }
+}
+
+/// 录像头（MPQ用户数据）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayHeader {
+    pub version: ReplayVersion,
+    pub elapsed_game_loops: u64,
+}
+
+impl ReplayHeader {
+    pub fn parse(content: &[u8]) -> anyhow::Result<Self> {
+        let value = VersionedDecoder::new(content).read_value()?;
+        let version = value.field(1).context("录像头缺少版本信息")?;
+        let get = |tag| version.field(tag).and_then(|v| v.as_int()).unwrap_or(0) as u32;
+
+        Ok(Self {
+            version: ReplayVersion {
+                major: get(1),
+                minor: get(2),
+                revision: get(3),
+                build: get(4),
+                base_build: get(5),
+            },
+            elapsed_game_loops: value.field(3).and_then(|v| v.as_int()).unwrap_or(0) as u64,
+        })
+    }
+}
+
+/// replay.details中的玩家
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerInfo {
+    pub name: String,
+    pub region: u32,
+    pub realm: u32,
+    pub toon_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayDetails {
+    pub players: Vec<PlayerInfo>,
+}
+
+impl ReplayDetails {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let value = VersionedDecoder::new(data).read_value()?;
+        let mut players = Vec::new();
+
+        for player in value.field(0).and_then(|v| v.as_array()).unwrap_or(&[]) {
+            let name = player
+                .field(0)
+                .and_then(|v| v.as_blob())
+                .map(|b| String::from_utf8_lossy(b).into_owned())
+                .unwrap_or_default();
+            let toon = player.field(1);
+            let toon_int = |tag| toon.and_then(|t| t.field(tag)).and_then(|v| v.as_int()).unwrap_or(0);
+
+            players.push(PlayerInfo {
+                name: strip_clan_tag(&name),
+                region: toon_int(0) as u32,
+                realm: toon_int(2) as u32,
+                toon_id: toon_int(3) as u64,
+            });
+        }
+
+        Ok(Self { players })
+    }
+}
+
+// 去掉名字前的战队标签，如"&lt;TAG&gt;<sp/>Name"
+fn strip_clan_tag(name: &str) -> String {
+    name.rsplit("<sp/>").next().unwrap_or(name).to_string()
+}
+
+/// 读取录像头
+pub fn read_header(path: &Path) -> anyhow::Result<ReplayHeader> {
+    let archive = MpqArchive::open(path)?;
+    ReplayHeader::parse(archive.user_data_content())
+}
+
+/// 读取录像中的玩家信息
+pub fn read_details(path: &Path) -> anyhow::Result<ReplayDetails> {
+    let archive = MpqArchive::open(path)?;
+    ReplayDetails::parse(&archive.read_file("replay.details")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_vint() {
+        let mut decoder = VersionedDecoder::new(&[0x0A, 0x1E, 0x03, 0x80, 0x01]);
+        assert_eq!(decoder.read_vint().unwrap(), 5);
+        assert_eq!(decoder.read_vint().unwrap(), 15);
+        assert_eq!(decoder.read_vint().unwrap(), -1);
+        assert_eq!(decoder.read_vint().unwrap(), 64);
+
+        for value in [0, 5, -1, 64, 95687, -123456789] {
+            assert_eq!(VersionedDecoder::new(&encode_vint(value)).read_vint().unwrap(), value);
+        }
+        assert_eq!(encode_vint(95687), vec![0x8E, 0xD7, 0x0B]);
+    }
+
+    #[test]
+    fn test_parse_header_version() {
+        // 结构体{1: {1: 5, 2: 0, 3: 15, 4: 95687, 5: 95687}, 3: 100}
+        let build = [0x09, 0x8E, 0xD7, 0x0B];
+        let mut data = vec![0x05, 0x04, 0x02, 0x05, 0x0A];
+        data.extend_from_slice(&[0x02, 0x09, 0x0A, 0x04, 0x09, 0x00, 0x06, 0x09, 0x1E, 0x08]);
+        data.extend_from_slice(&build);
+        data.push(0x0A);
+        data.extend_from_slice(&build);
+        data.extend_from_slice(&[0x06, 0x09, 0xC8, 0x01]);
+
+        let header = ReplayHeader::parse(&data).unwrap();
+        assert_eq!(header.version.to_string(), "5.0.15.95687");
+        assert_eq!(header.version.base_build, 95687);
+        assert_eq!(header.elapsed_game_loops, 100);
+
+        let (range, value) = find_int_field(&data, &[1, 4]).unwrap().unwrap();
+        assert_eq!(value, 95687);
+        assert_eq!(&data[range], &build);
+        assert_eq!(find_int_field(&data, &[3]).unwrap().map(|(_, v)| v), Some(100));
+        assert_eq!(find_int_field(&data, &[2]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_strip_clan_tag() {
+        assert_eq!(strip_clan_tag("&lt;ABC&gt;<sp/>Alice"), "Alice");
+        assert_eq!(strip_clan_tag("Alice"), "Alice");
+    }
+}
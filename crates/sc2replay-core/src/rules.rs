@@ -0,0 +1,318 @@
+use std::path::Path;
+use std::sync::Arc;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::builds;
+use crate::fixer::{SCAN_LIMIT, SEARCH_BYTES, TARGET_BYTES};
+use crate::patch::{Patch, ReplaceBytes, ReplaceInFile, SetHeaderField};
+
+/*
+    修复规则文件（TOML）示例：
+
+    [[rule]]
+    id = "cn-5.0.15.95687"
+    description = "CN服5.0.15.95687录像版本号修正"
+    search = "09 00 04 09 00 06 09 00"
+    replace = "09 0A 04 09 00 06 09 1E"
+    scan_limit = 128
+    base_build = [95687]
+
+    type指定修复操作，默认为bytes：
+    bytes         在文件开头scan_limit字节内替换search为replace，长度不同时只能在录像头内
+    header_field  把录像头中field路径的整数字段改为to，如field = [1, 4]为build；可选from限定当前值
+    file_bytes    在归档内的file（如"replay.details"）中替换search为replace，长度可以不同
+
+    base_build可选，限定规则只用于录像头中base build为其中之一的录像；
+    version可选，如version = ["5.0.15"]，按版本表查出录像的版本号，限定为其中之一或以其开头的版本，
+    版本表中没有的base build不适用。录像头无法解析时两者都不限定
+*/
+
+/// 一条修复规则
+#[derive(Debug, Clone)]
+pub struct PatchRule {
+    pub id: String,
+    pub description: String,
+    pub patch: Arc<dyn Patch>,
+    /// 适用的base build，为空时不限定
+    pub base_builds: Vec<u32>,
+    /// 适用的版本号或其前缀，如"5.0.15"，按版本表匹配，为空时不限定
+    pub versions: Vec<String>,
+}
+
+impl PatchRule {
+    /// 规则是否适用于该base build的录像
+    pub fn applies_to(&self, base_build: u32) -> bool {
+        if !self.base_builds.is_empty() && !self.base_builds.contains(&base_build) {
+            return false;
+        }
+        if self.versions.is_empty() {
+            return true;
+        }
+        builds::lookup(base_build).iter().any(|info| self.versions.iter().any(|v| info.matches_version(v)))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RuleFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleDefinition>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RuleKind {
+    #[default]
+    Bytes,
+    HeaderField,
+    FileBytes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RuleDefinition {
+    id: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default, rename = "type")]
+    kind: RuleKind,
+    #[serde(default)]
+    search: Option<String>,
+    #[serde(default)]
+    replace: Option<String>,
+    #[serde(default)]
+    scan_limit: Option<usize>,
+    #[serde(default)]
+    field: Option<Vec<i64>>,
+    #[serde(default)]
+    from: Option<i64>,
+    #[serde(default)]
+    to: Option<i64>,
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default, rename = "base_build", skip_serializing_if = "Vec::is_empty")]
+    base_builds: Vec<u32>,
+    #[serde(default, rename = "version", skip_serializing_if = "Vec::is_empty")]
+    versions: Vec<String>,
+}
+
+impl RuleDefinition {
+    // 按type生成修复操作，缺少的字段报错
+    fn to_patch(&self) -> anyhow::Result<Arc<dyn Patch>> {
+        let hex = |name: &str, value: &Option<String>| -> anyhow::Result<Vec<u8>> {
+            let text = value.as_deref().with_context(|| format!("缺少{}", name))?;
+            let bytes = parse_hex(text).with_context(|| format!("{}无效", name))?;
+            if bytes.is_empty() && name == "search" {
+                return Err(anyhow::anyhow!("search为空"));
+            }
+            Ok(bytes)
+        };
+
+        Ok(match self.kind {
+            RuleKind::Bytes => Arc::new(ReplaceBytes {
+                search: hex("search", &self.search)?,
+                replace: hex("replace", &self.replace)?,
+                scan_limit: self.scan_limit.unwrap_or(SCAN_LIMIT),
+            }),
+            RuleKind::HeaderField => {
+                let field = self.field.clone().filter(|f| !f.is_empty()).context("缺少field")?;
+                Arc::new(SetHeaderField {
+                    field,
+                    from: self.from,
+                    to: self.to.context("缺少to")?,
+                })
+            }
+            RuleKind::FileBytes => Arc::new(ReplaceInFile {
+                file: self.file.clone().context("缺少file")?,
+                search: hex("search", &self.search)?,
+                replace: hex("replace", &self.replace)?,
+            }),
+        })
+    }
+}
+
+/// 内置规则，修正为SC2.5.0.15.95687版本，不限定录像的版本
+pub fn builtin_rules() -> Vec<PatchRule> {
+    vec![PatchRule {
+        id: "cn-5.0.15.95687".to_string(),
+        description: "CN服5.0.15.95687录像版本号修正".to_string(),
+        patch: Arc::new(ReplaceBytes {
+            search: SEARCH_BYTES.to_vec(),
+            replace: TARGET_BYTES.to_vec(),
+            scan_limit: SCAN_LIMIT,
+        }),
+        base_builds: Vec::new(),
+        versions: Vec::new(),
+    }]
+}
+
+/// 读取规则文件
+pub fn load_rule_file(path: &Path) -> anyhow::Result<Vec<PatchRule>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("无法读取规则文件: {}", path.display()))?;
+    parse_rules(&content).with_context(|| format!("规则文件格式错误: {}", path.display()))
+}
+
+pub fn parse_rules(content: &str) -> anyhow::Result<Vec<PatchRule>> {
+    let file: RuleFile = toml::from_str(content)?;
+    let mut rules = Vec::new();
+
+    for definition in file.rules {
+        let patch = definition
+            .to_patch()
+            .with_context(|| format!("规则{}无效", definition.id))?;
+        rules.push(PatchRule {
+            id: definition.id,
+            description: definition.description,
+            patch,
+            base_builds: definition.base_builds,
+            versions: definition.versions,
+        });
+    }
+
+    Ok(rules)
+}
+
+/// 写出一条字节替换规则，格式同规则文件
+pub fn format_bytes_rule(
+    id: &str,
+    description: &str,
+    search: &[u8],
+    replace: &[u8],
+    scan_limit: usize,
+    base_builds: &[u32],
+) -> anyhow::Result<String> {
+    let file = RuleFile {
+        rules: vec![RuleDefinition {
+            id: id.to_string(),
+            description: description.to_string(),
+            kind: RuleKind::Bytes,
+            search: Some(format_hex(search)),
+            replace: Some(format_hex(replace)),
+            scan_limit: Some(scan_limit),
+            field: None,
+            from: None,
+            to: None,
+            file: None,
+            base_builds: base_builds.to_vec(),
+            versions: Vec::new(),
+        }],
+    };
+    Ok(toml::to_string(&file)?)
+}
+
+/// 内置规则加上规则文件中的规则，读取失败的文件返回错误信息
+pub fn load_rules(rule_files: &[impl AsRef<Path>]) -> (Vec<PatchRule>, Vec<String>) {
+    let mut rules = builtin_rules();
+    let mut errors = Vec::new();
+
+    for path in rule_files {
+        match load_rule_file(path.as_ref()) {
+            Ok(loaded) => rules.extend(loaded),
+            Err(e) => errors.push(format!("{:#}", e)),
+        }
+    }
+
+    (rules, errors)
+}
+
+/// 解析"09 0A 04"或"090A04"形式的十六进制字节
+pub fn parse_hex(text: &str) -> anyhow::Result<Vec<u8>> {
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(anyhow::anyhow!("十六进制字符数必须为偶数"));
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| anyhow::anyhow!("{}", e)))
+        .collect()
+}
+
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(
+            r#"
+            [[rule]]
+            id = "test"
+            search = "09 00 04"
+            replace = "090A04"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].patch.describe(), format!("替换字节 09 00 04 -> 09 0A 04（前{}字节）", SCAN_LIMIT));
+        assert_eq!(format_hex(&[0x09, 0x0A, 0x04]), "09 0A 04");
+    }
+
+    #[test]
+    fn test_parse_rule_types() {
+        let rules = parse_rules(
+            r#"
+            [[rule]]
+            id = "length"
+            search = "09 00"
+            replace = "09"
+
+            [[rule]]
+            id = "build"
+            type = "header_field"
+            field = [1, 4]
+            from = 95687
+            to = 95841
+
+            [[rule]]
+            id = "details"
+            type = "file_bytes"
+            file = "replay.details"
+            search = "00"
+            replace = "01 02"
+            "#,
+        )
+        .unwrap();
+        let described: Vec<String> = rules.iter().map(|r| r.patch.describe()).collect();
+        assert_eq!(described[1], "录像头字段1.4: 95687 -> 95841");
+        assert_eq!(described[2], "replay.details中替换字节 00 -> 01 02");
+
+        // 缺少该类型需要的字段
+        let result = parse_rules(
+            r#"
+            [[rule]]
+            id = "bad"
+            type = "header_field"
+            field = [1, 4]
+            "#,
+        );
+        assert!(result.is_err());
+        assert!(parse_rules("[[rule]]\nid = \"bad\"\nsearch = \"\"\nreplace = \"\"").is_err());
+    }
+
+    #[test]
+    fn test_format_bytes_rule() {
+        let text = format_bytes_rule("test", "说明", &[0x09, 0x00], &[0x09, 0x0A, 0x00], 64, &[95687]).unwrap();
+        let rules = parse_rules(&text).unwrap();
+        assert_eq!(rules[0].id, "test");
+        assert_eq!(rules[0].description, "说明");
+        assert_eq!(rules[0].patch.describe(), "替换字节 09 00 -> 09 0A 00（前64字节）");
+        assert_eq!(rules[0].base_builds, vec![95687]);
+    }
+
+    #[test]
+    fn test_rule_versions() {
+        let rules = parse_rules("[[rule]]\nid = \"v\"\nsearch = \"00\"\nreplace = \"01\"\nversion = [\"5.0.1\", \"5.0.14\"]").unwrap();
+        assert_eq!(rules[0].versions, vec!["5.0.1", "5.0.14"]);
+        // 按版本表匹配，"5.0.1"不匹配5.0.15
+        assert!(rules[0].applies_to(93333));
+        assert!(!rules[0].applies_to(95687));
+        assert!(!rules[0].applies_to(1));
+        assert!(builtin_rules()[0].applies_to(1));
+    }
+}
@@ -0,0 +1,222 @@
+//! 修复规则推导（开发用）
+//!
+//! 新的客户端补丁导致录像无法播放时，用一个无法播放的录像和一个目标版本的正常录像，
+//! 比较两者录像头中的版本字段，生成候选的字节替换规则和测试用例。
+//! 规则把坏录像中这些字段所在的一段字节替换为正常录像中的值，前面补足上下文，保证在查找范围内唯一。
+
+use std::ops::Range;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::fixer::{apply_rules, SCAN_LIMIT};
+use crate::mpq::{UserDataHeader, USER_DATA_HEADER_SIZE};
+use crate::patch::ReplayEditor;
+use crate::replay::{encode_vint, find_int_field, ReplayHeader};
+use crate::rules::{format_bytes_rule, format_hex, parse_hex, parse_rules, PatchRule};
+
+/// 比较的录像头字段：名称和标签路径
+const VERSION_FIELDS: &[(&str, &[i64])] = &[
+    ("major", &[1, 1]),
+    ("minor", &[1, 2]),
+    ("revision", &[1, 3]),
+    ("build", &[1, 4]),
+    ("base_build", &[1, 5]),
+    ("data_build", &[6]),
+];
+
+/// 两个录像中取值不同的录像头字段
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldDiff {
+    pub name: &'static str,
+    pub broken: i64,
+    pub good: i64,
+}
+
+/// 推导结果
+#[derive(Debug, Clone)]
+pub struct Derivation {
+    pub rule_id: String,
+    pub diffs: Vec<FieldDiff>,
+    /// 规则文件内容，可以直接加载
+    pub rule_file: String,
+    pub fixture: RuleFixture,
+}
+
+/// 规则的测试用例：坏录像的开头和应用规则后应得的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleFixture {
+    pub rule: String,
+    /// 坏录像到归档头为止的部分，十六进制
+    pub input: String,
+    pub expected: String,
+    /// 修复后录像头中的版本，如"5.0.15.95687"
+    pub expected_version: String,
+}
+
+impl RuleFixture {
+    /// 用规则修复input，检查结果与expected相同、录像头版本为expected_version
+    pub fn check(&self, rules: &[PatchRule]) -> anyhow::Result<()> {
+        let mut replay = ReplayEditor::new(parse_hex(&self.input).context("input无效")?);
+        let (rule, _) = apply_rules(&mut replay, rules)?.context("没有适用的规则")?;
+        if rule.id != self.rule {
+            return Err(anyhow::anyhow!("应用的规则为{}，应为{}", rule.id, self.rule));
+        }
+
+        let output = replay.into_bytes();
+        if output != parse_hex(&self.expected).context("expected无效")? {
+            return Err(anyhow::anyhow!("修复结果与expected不同"));
+        }
+        let version = read_header(&output)?.version.to_string();
+        if version != self.expected_version {
+            return Err(anyhow::anyhow!("修复后版本为{}，应为{}", version, self.expected_version));
+        }
+        Ok(())
+    }
+}
+
+// 录像头内容及其在文件中的范围
+fn header_content(data: &[u8]) -> anyhow::Result<(&[u8], Range<usize>)> {
+    let range = UserDataHeader::parse(data)?.content_range();
+    let content = data.get(range.clone()).context("录像头长度超出文件")?;
+    Ok((content, range))
+}
+
+fn read_header(data: &[u8]) -> anyhow::Result<ReplayHeader> {
+    ReplayHeader::parse(header_content(data)?.0)
+}
+
+// data开头limit个字节内search出现的次数
+fn count_within(data: &[u8], search: &[u8], limit: usize) -> usize {
+    let data = &data[..data.len().min(limit)];
+    data.windows(search.len()).filter(|w| *w == search).count()
+}
+
+/// 比较坏录像和正常录像的录像头，推导候选规则和测试用例
+pub fn derive_rule(broken: &[u8], good: &[u8]) -> anyhow::Result<Derivation> {
+    let (broken_content, broken_range) = header_content(broken).context("无法读取坏录像的录像头")?;
+    let (good_content, _) = header_content(good).context("无法读取正常录像的录像头")?;
+    let broken_header = ReplayHeader::parse(broken_content).context("无法解析坏录像的录像头")?;
+    let good_header = ReplayHeader::parse(good_content).context("无法解析正常录像的录像头")?;
+
+    // 不同的字段及其在坏录像中的位置和新的编码
+    let mut diffs = Vec::new();
+    let mut edits = Vec::new();
+    for (name, field) in VERSION_FIELDS {
+        let (Some((range, broken_value)), Some((_, good_value))) =
+            (find_int_field(broken_content, field)?, find_int_field(good_content, field)?)
+        else {
+            continue;
+        };
+        if broken_value == good_value {
+            continue;
+        }
+        if broken_content[range.start] != 0x09 {
+            return Err(anyhow::anyhow!("录像头字段{}不是变长整数", name));
+        }
+        let mut encoded = vec![0x09];
+        encoded.extend(encode_vint(good_value));
+        diffs.push(FieldDiff { name, broken: broken_value, good: good_value });
+        edits.push((range, encoded));
+    }
+    if edits.is_empty() {
+        return Err(anyhow::anyhow!("两个录像的版本字段相同，无法推导规则"));
+    }
+    edits.sort_by_key(|(range, _)| range.start);
+
+    // 覆盖所有不同字段的一段，在文件中的范围
+    let offset = broken_range.start;
+    let span = offset + edits[0].0.start..offset + edits[edits.len() - 1].0.end;
+    let scan_limit = SCAN_LIMIT.max(span.end.next_multiple_of(64));
+
+    // 向前补足上下文，直到在坏录像中唯一且不出现在正常录像中
+    let mut start = span.start;
+    while count_within(broken, &broken[start..span.end], scan_limit) > 1
+        || count_within(good, &broken[start..span.end], scan_limit) > 0
+    {
+        if start <= USER_DATA_HEADER_SIZE {
+            return Err(anyhow::anyhow!("无法找到唯一的字节序列"));
+        }
+        start -= 1;
+    }
+
+    let search = broken[start..span.end].to_vec();
+    let mut replace = broken[start..span.start].to_vec();
+    let mut pos = span.start;
+    for (range, encoded) in &edits {
+        replace.extend_from_slice(&broken[pos..offset + range.start]);
+        replace.extend_from_slice(encoded);
+        pos = offset + range.end;
+    }
+
+    let rule_id = format!("auto-{}", good_header.version);
+    let description = format!("由样本录像推导: {} -> {}", broken_header.version, good_header.version);
+    let mut rule_file = String::from("# 由样本录像推导的候选规则，使用前请用更多录像验证\n");
+    for diff in &diffs {
+        rule_file.push_str(&format!("# {}: {} -> {}\n", diff.name, diff.broken, diff.good));
+    }
+    // 规则只用于与坏录像base build相同的录像
+    let base_builds: Vec<u32> = Some(broken_header.version.base_build).filter(|b| *b != 0).into_iter().collect();
+    rule_file.push_str(&format_bytes_rule(&rule_id, &description, &search, &replace, scan_limit, &base_builds)?);
+
+    // 用推导出的规则修复坏录像的开头，作为测试用例并检查修复后的版本
+    let input = &broken[..(UserDataHeader::parse(broken)?.archive_offset as usize).clamp(broken_range.end, broken.len())];
+    let rules = parse_rules(&rule_file)?;
+    let mut replay = ReplayEditor::new(input.to_vec());
+    apply_rules(&mut replay, &rules)?.context("推导出的规则不适用于坏录像")?;
+    let fixture = RuleFixture {
+        rule: rule_id.clone(),
+        input: format_hex(input),
+        expected: format_hex(replay.data()),
+        expected_version: good_header.version.to_string(),
+    };
+    fixture.check(&rules).context("推导出的规则未能得到正常录像的版本")?;
+
+    Ok(Derivation { rule_id, diffs, rule_file, fixture })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixer::{SEARCH_BYTES, TARGET_BYTES};
+    use crate::mpq::tests::build_archive;
+
+    // 结构体{1: {1: major, 2: 0, 3: revision, 4: 95687, 5: 95687}, 3: 100}
+    fn replay(major: u8, revision: u8) -> Vec<u8> {
+        let build = [0x09, 0x8E, 0xD7, 0x0B];
+        let mut header = vec![0x05, 0x04, 0x02, 0x05, 0x0A];
+        header.extend_from_slice(&[0x02, 0x09, major << 1, 0x04, 0x09, 0x00, 0x06, 0x09, revision << 1, 0x08]);
+        header.extend_from_slice(&build);
+        header.push(0x0A);
+        header.extend_from_slice(&build);
+        header.extend_from_slice(&[0x06, 0x09, 0xC8, 0x01]);
+        build_archive(&header, "replay.details", b"details")
+    }
+
+    #[test]
+    fn test_derive_builtin_rule() {
+        // 内置规则对应的情况：major和revision被写成0
+        let derivation = derive_rule(&replay(0, 0), &replay(5, 15)).unwrap();
+        assert_eq!(derivation.rule_id, "auto-5.0.15.95687");
+        assert_eq!(
+            derivation.diffs,
+            vec![
+                FieldDiff { name: "major", broken: 0, good: 5 },
+                FieldDiff { name: "revision", broken: 0, good: 15 },
+            ]
+        );
+
+        let rules = parse_rules(&derivation.rule_file).unwrap();
+        assert_eq!(rules[0].base_builds, vec![95687]);
+        assert_eq!(
+            rules[0].patch.describe(),
+            format!("替换字节 {} -> {}（前{}字节）", format_hex(SEARCH_BYTES), format_hex(TARGET_BYTES), SCAN_LIMIT)
+        );
+
+        // 测试用例可以写出再读回
+        let fixture: RuleFixture = toml::from_str(&toml::to_string(&derivation.fixture).unwrap()).unwrap();
+        fixture.check(&rules).unwrap();
+        assert!(fixture.check(&crate::rules::builtin_rules()).is_err());
+
+        assert!(derive_rule(&replay(5, 15), &replay(5, 15)).is_err());
+    }
+}